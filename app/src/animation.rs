@@ -0,0 +1,287 @@
+//! Short frame animations for the matrix.
+//!
+//! Frames are stored compactly as five rows of bits, in the same layout as
+//! font glyphs, with a level to show the set bits at and how long the frame
+//! is held for. Animations are plain data so that they can be declared as
+//! constants and live in flash.
+
+use crate::display::{Image, MAX_LEVEL, ROWS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub bits: [u8; ROWS],
+    pub level: u8,
+    pub duration_ms: u16,
+}
+
+impl Frame {
+    pub const fn new(bits: [u8; ROWS], duration_ms: u16) -> Self {
+        Self::with_level(bits, MAX_LEVEL, duration_ms)
+    }
+
+    pub const fn with_level(bits: [u8; ROWS], level: u8, duration_ms: u16) -> Self {
+        Self {
+            bits,
+            level,
+            duration_ms,
+        }
+    }
+
+    pub const fn image(&self) -> Image {
+        Image::from_bits(&self.bits, self.level)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Playback {
+    /// Play through once and hold the last frame.
+    Once,
+    /// Restart from the first frame after the last.
+    Loop,
+    /// Play forwards then backwards, without repeating the end frames.
+    PingPong,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Animation<'a> {
+    pub frames: &'a [Frame],
+    pub playback: Playback,
+}
+
+impl<'a> Animation<'a> {
+    pub const fn new(frames: &'a [Frame], playback: Playback) -> Self {
+        Self { frames, playback }
+    }
+}
+
+pub const SPINNER: Animation<'static> = Animation::new(
+    &[
+        Frame::new([0b00100, 0b00100, 0b00100, 0b00000, 0b00000], 100),
+        Frame::new([0b00001, 0b00010, 0b00100, 0b00000, 0b00000], 100),
+        Frame::new([0b00000, 0b00000, 0b00111, 0b00000, 0b00000], 100),
+        Frame::new([0b00000, 0b00000, 0b00100, 0b00010, 0b00001], 100),
+        Frame::new([0b00000, 0b00000, 0b00100, 0b00100, 0b00100], 100),
+        Frame::new([0b00000, 0b00000, 0b00100, 0b01000, 0b10000], 100),
+        Frame::new([0b00000, 0b00000, 0b11100, 0b00000, 0b00000], 100),
+        Frame::new([0b10000, 0b01000, 0b00100, 0b00000, 0b00000], 100),
+    ],
+    Playback::Loop,
+);
+
+pub const HEARTBEAT: Animation<'static> = Animation::new(
+    &[
+        Frame::with_level([0b00000, 0b01010, 0b01110, 0b00100, 0b00000], 3, 400),
+        Frame::with_level([0b01010, 0b11111, 0b11111, 0b01110, 0b00100], 6, 100),
+        Frame::new([0b01010, 0b11111, 0b11111, 0b01110, 0b00100], 200),
+    ],
+    Playback::PingPong,
+);
+
+pub const ARROW_RIGHT: Animation<'static> = Animation::new(
+    &[
+        Frame::new([0b00000, 0b00000, 0b10000, 0b00000, 0b00000], 100),
+        Frame::new([0b10000, 0b01000, 0b11100, 0b01000, 0b10000], 100),
+        Frame::new([0b01000, 0b00100, 0b11110, 0b00100, 0b01000], 100),
+        Frame::new([0b00100, 0b00010, 0b11111, 0b00010, 0b00100], 300),
+        Frame::new([0b00000, 0b00000, 0b00000, 0b00000, 0b00000], 200),
+    ],
+    Playback::Loop,
+);
+
+/// Steps through an animation as time passes.
+#[derive(Clone, Debug)]
+pub struct Player<'a> {
+    animation: Animation<'a>,
+    index: usize,
+    forwards: bool,
+    elapsed_ms: u32,
+    finished: bool,
+}
+
+impl<'a> Player<'a> {
+    pub fn new(animation: Animation<'a>) -> Self {
+        Self {
+            animation,
+            index: 0,
+            forwards: true,
+            elapsed_ms: 0,
+            finished: animation.frames.is_empty(),
+        }
+    }
+
+    /// The image for the current frame.
+    pub fn image(&self) -> Image {
+        self.animation
+            .frames
+            .get(self.index)
+            .map(Frame::image)
+            .unwrap_or_default()
+    }
+
+    /// Only a `Playback::Once` animation can finish.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Let time pass, returning the new image if the frame has changed.
+    pub fn advance(&mut self, ms: u32) -> Option<Image> {
+        if self.finished {
+            return None;
+        }
+        self.elapsed_ms += ms;
+
+        let start = self.index;
+        loop {
+            // A zero duration would otherwise never let time catch up
+            let duration_ms = u32::from(self.animation.frames[self.index].duration_ms).max(1);
+            if self.elapsed_ms < duration_ms {
+                break;
+            }
+            if !self.step() {
+                self.finished = true;
+                self.elapsed_ms = 0;
+                break;
+            }
+            self.elapsed_ms -= duration_ms;
+        }
+
+        if self.index != start {
+            Some(self.image())
+        } else {
+            None
+        }
+    }
+
+    /// Move to the next frame, returning false if there isn't one.
+    fn step(&mut self) -> bool {
+        let last = self.animation.frames.len() - 1;
+        match self.animation.playback {
+            Playback::Once if self.index == last => return false,
+            Playback::Once => self.index += 1,
+            Playback::Loop => {
+                self.index = if self.index == last {
+                    0
+                } else {
+                    self.index + 1
+                }
+            }
+            Playback::PingPong if last == 0 => {}
+            Playback::PingPong => {
+                if self.forwards && self.index == last {
+                    self.forwards = false;
+                } else if !self.forwards && self.index == 0 {
+                    self.forwards = true;
+                }
+                if self.forwards {
+                    self.index += 1;
+                } else {
+                    self.index -= 1;
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAMES: [Frame; 3] = [
+        Frame::new([0b10000, 0, 0, 0, 0], 10),
+        Frame::new([0b01000, 0, 0, 0, 0], 20),
+        Frame::new([0b00100, 0, 0, 0, 0], 10),
+    ];
+
+    fn indices(animation: Animation, steps: usize) -> Vec<usize> {
+        let mut player = Player::new(animation);
+        let mut indices = vec![0];
+        for _ in 0..steps {
+            player.advance(20);
+            indices.push(player.index);
+        }
+        indices
+    }
+
+    #[test]
+    fn test_frame_image() {
+        let frame = Frame::with_level([0b10001, 0, 0, 0, 0b00100], 5, 100);
+        assert_eq!(
+            frame.image(),
+            Image::new([
+                [5, 0, 0, 0, 5],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 5, 0, 0],
+            ])
+        );
+    }
+
+    #[test]
+    fn test_frames_are_held_for_their_duration() {
+        let mut player = Player::new(Animation::new(&FRAMES, Playback::Loop));
+        assert_eq!(player.advance(9), None);
+        assert_eq!(player.advance(1), Some(FRAMES[1].image()));
+        assert_eq!(player.advance(19), None);
+        assert_eq!(player.advance(1), Some(FRAMES[2].image()));
+    }
+
+    #[test]
+    fn test_once_holds_the_last_frame() {
+        let mut player = Player::new(Animation::new(&FRAMES, Playback::Once));
+        assert!(player.advance(1_000).is_some());
+        assert!(player.is_finished());
+        assert_eq!(player.image(), FRAMES[2].image());
+        assert_eq!(player.advance(1_000), None);
+    }
+
+    #[test]
+    fn test_loop_wraps_to_the_first_frame() {
+        let frames = [Frame::new([0; ROWS], 20); 3];
+        assert_eq!(
+            indices(Animation::new(&frames, Playback::Loop), 5),
+            vec![0, 1, 2, 0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_ping_pong_reverses_at_each_end() {
+        let frames = [Frame::new([0; ROWS], 20); 3];
+        assert_eq!(
+            indices(Animation::new(&frames, Playback::PingPong), 8),
+            vec![0, 1, 2, 1, 0, 1, 2, 1, 0]
+        );
+    }
+
+    #[test]
+    fn test_single_frame_ping_pong_stays_put() {
+        let frames = [Frame::new([0; ROWS], 20)];
+        assert_eq!(
+            indices(Animation::new(&frames, Playback::PingPong), 3),
+            vec![0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_empty_animation_is_finished() {
+        let mut player = Player::new(Animation::new(&[], Playback::Loop));
+        assert!(player.is_finished());
+        assert_eq!(player.image(), Image::blank());
+        assert_eq!(player.advance(100), None);
+    }
+
+    #[test]
+    fn test_zero_durations_do_not_hang() {
+        let frames = [Frame::new([0; ROWS], 0); 2];
+        let mut player = Player::new(Animation::new(&frames, Playback::Loop));
+        player.advance(1_000);
+    }
+
+    #[test]
+    fn test_builtin_animations_have_frames() {
+        for animation in [SPINNER, HEARTBEAT, ARROW_RIGHT].iter() {
+            assert!(!animation.frames.is_empty());
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! What a firmware image was built from: its version, the git commit and
+//! whether the tree had changes, the build profile and the features.
+//!
+//! The firmware keeps this in flash at a fixed address, starting with a
+//! magic number, so that tools can pick it out of an ELF or a flash dump
+//! without the board running. Text is held NUL padded, and cut short if it
+//! won't fit.
+
+use core::fmt;
+
+/// Starts every `BuildInfo`, to be searched for.
+pub const MAGIC: [u8; 8] = *b"uRSbuild";
+
+/// The version of the layout below, bumped whenever it changes.
+pub const LAYOUT: u32 = 1;
+
+pub const VERSION_LEN: usize = 16;
+/// Room for a full SHA-1 commit hash in hex.
+pub const COMMIT_LEN: usize = 40;
+pub const PROFILE_LEN: usize = 8;
+pub const FEATURES_LEN: usize = 32;
+
+/// The size of a `BuildInfo` in memory, and in an image.
+pub const SIZE: usize = 16 + VERSION_LEN + COMMIT_LEN + PROFILE_LEN + FEATURES_LEN;
+
+const VERSION_AT: usize = 16;
+const COMMIT_AT: usize = VERSION_AT + VERSION_LEN;
+const PROFILE_AT: usize = COMMIT_AT + COMMIT_LEN;
+const FEATURES_AT: usize = PROFILE_AT + PROFILE_LEN;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuildInfo {
+    magic: [u8; 8],
+    layout: u32,
+    /// Non-zero if the tree had uncommitted changes.
+    dirty: u32,
+    version: [u8; VERSION_LEN],
+    commit: [u8; COMMIT_LEN],
+    profile: [u8; PROFILE_LEN],
+    /// Separated by commas.
+    features: [u8; FEATURES_LEN],
+}
+
+impl BuildInfo {
+    /// An empty `commit` is shown as unknown, such as when building from
+    /// outside of git.
+    pub const fn new(
+        version: &str,
+        commit: &str,
+        dirty: bool,
+        profile: &str,
+        features: &str,
+    ) -> Self {
+        Self {
+            magic: MAGIC,
+            layout: LAYOUT,
+            dirty: dirty as u32,
+            version: padded(version),
+            commit: padded(commit),
+            profile: padded(profile),
+            features: padded(features),
+        }
+    }
+
+    pub fn version(&self) -> &str {
+        text(&self.version)
+    }
+
+    pub fn commit(&self) -> &str {
+        text(&self.commit)
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty != 0
+    }
+
+    pub fn profile(&self) -> &str {
+        text(&self.profile)
+    }
+
+    pub fn features(&self) -> impl Iterator<Item = &str> {
+        text(&self.features)
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+    }
+
+    /// The bytes as laid out in the nRF's little endian memory.
+    pub fn to_bytes(&self) -> [u8; SIZE] {
+        let mut bytes = [0; SIZE];
+        bytes[..8].copy_from_slice(&self.magic);
+        bytes[8..12].copy_from_slice(&self.layout.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.dirty.to_le_bytes());
+        bytes[VERSION_AT..COMMIT_AT].copy_from_slice(&self.version);
+        bytes[COMMIT_AT..PROFILE_AT].copy_from_slice(&self.commit);
+        bytes[PROFILE_AT..FEATURES_AT].copy_from_slice(&self.profile);
+        bytes[FEATURES_AT..].copy_from_slice(&self.features);
+        bytes
+    }
+
+    /// Read back what `to_bytes` gives, if it starts with the magic number
+    /// and has this layout.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..SIZE)?;
+        let word = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+        if bytes[..8] != MAGIC || word(8) != LAYOUT {
+            return None;
+        }
+        let mut info = Self::new("", "", word(12) != 0, "", "");
+        info.version.copy_from_slice(&bytes[VERSION_AT..COMMIT_AT]);
+        info.commit.copy_from_slice(&bytes[COMMIT_AT..PROFILE_AT]);
+        info.profile
+            .copy_from_slice(&bytes[PROFILE_AT..FEATURES_AT]);
+        info.features.copy_from_slice(&bytes[FEATURES_AT..SIZE]);
+        Some(info)
+    }
+
+    /// Search a firmware image, such as an ELF file or a flash dump, for
+    /// the first `BuildInfo` in it.
+    pub fn find(image: &[u8]) -> Option<Self> {
+        image
+            .windows(MAGIC.len())
+            .enumerate()
+            .filter(|(_, window)| *window == MAGIC)
+            .find_map(|(at, _)| Self::from_bytes(&image[at..]))
+    }
+}
+
+/// Shows as, for example, `0.1.0 (3f2a9c1, release, v2)`, with `-dirty`
+/// after the commit if the tree had changes.
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (", self.version())?;
+        match self.commit() {
+            "" => f.write_str("unknown commit")?,
+            commit => f.write_str(commit)?,
+        }
+        if self.is_dirty() {
+            f.write_str("-dirty")?;
+        }
+        write!(f, ", {}", self.profile())?;
+        for (index, feature) in self.features().enumerate() {
+            f.write_str(if index == 0 { ", " } else { "+" })?;
+            f.write_str(feature)?;
+        }
+        f.write_str(")")
+    }
+}
+
+const fn padded<const N: usize>(text: &str) -> [u8; N] {
+    let bytes = text.as_bytes();
+    let mut padded = [0; N];
+    let mut i = 0;
+    while i < N && i < bytes.len() {
+        padded[i] = bytes[i];
+        i += 1;
+    }
+    padded
+}
+
+/// The text up to the padding, less any character cut short.
+fn text(bytes: &[u8]) -> &str {
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    match core::str::from_utf8(&bytes[..len]) {
+        Ok(text) => text,
+        Err(error) => core::str::from_utf8(&bytes[..error.valid_up_to()]).unwrap_or(""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> BuildInfo {
+        BuildInfo::new(
+            "0.1.0",
+            "3f2a9c1d2e4b5a6978695a4b3c2d1e0f9a8b7c6d",
+            true,
+            "release",
+            "default,v2",
+        )
+    }
+
+    #[test]
+    fn test_fields() {
+        let info = info();
+        assert_eq!(info.version(), "0.1.0");
+        assert_eq!(info.commit(), "3f2a9c1d2e4b5a6978695a4b3c2d1e0f9a8b7c6d");
+        assert!(info.is_dirty());
+        assert_eq!(info.profile(), "release");
+        assert_eq!(info.features().collect::<Vec<_>>(), ["default", "v2"]);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            info().to_string(),
+            "0.1.0 (3f2a9c1d2e4b5a6978695a4b3c2d1e0f9a8b7c6d-dirty, release, default+v2)"
+        );
+        assert_eq!(
+            BuildInfo::new("0.2.0", "", false, "debug", "").to_string(),
+            "0.2.0 (unknown commit, debug)"
+        );
+    }
+
+    #[test]
+    fn test_long_text_is_cut_short() {
+        let info = BuildInfo::new(
+            "0.1.0-a-rather-long-pre-release",
+            "",
+            false,
+            "débugging",
+            "",
+        );
+        assert_eq!(info.version(), "0.1.0-a-rather-l");
+        assert_eq!(info.profile(), "débuggi");
+    }
+
+    #[test]
+    fn test_bytes_match_the_layout_in_memory() {
+        let info = info();
+        assert_eq!(core::mem::size_of::<BuildInfo>(), SIZE);
+        let in_memory =
+            unsafe { core::slice::from_raw_parts(&info as *const BuildInfo as *const u8, SIZE) };
+        assert_eq!(in_memory, info.to_bytes());
+        assert_eq!(BuildInfo::from_bytes(&info.to_bytes()), Some(info));
+    }
+
+    #[test]
+    fn test_find_in_an_image() {
+        let mut image = vec![0xff; 300];
+        // A stray magic number, not followed by a known layout
+        image[10..18].copy_from_slice(&MAGIC);
+        image[100..100 + SIZE].copy_from_slice(&info().to_bytes());
+        assert_eq!(BuildInfo::find(&image), Some(info()));
+
+        image[108] = 2;
+        assert_eq!(BuildInfo::find(&image), None);
+        assert_eq!(BuildInfo::find(&[]), None);
+    }
+}
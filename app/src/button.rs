@@ -0,0 +1,416 @@
+//! Debouncing and gesture detection for a push button.
+//!
+//! The button is fed timestamped samples of its raw state, typically on
+//! each edge interrupt and then periodically, and reports events once the
+//! state has settled. A pair of buttons can also be watched together, so
+//! that pressing both at once is reported as a chord.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Pressed,
+    Released,
+    /// The button has been held down for the long press time. Reported
+    /// once per press, ahead of the eventual `Released`.
+    LongPress,
+    /// A press that follows a short click closely enough. Reported instead
+    /// of `Pressed`, and followed by `Released` as usual.
+    DoubleClick,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timings {
+    /// How long the raw state must hold steady before it is believed.
+    pub debounce_ms: u32,
+    /// How long the button must be held to report a long press.
+    pub long_press_ms: u32,
+    /// The most time allowed between releasing a click and pressing again
+    /// for the two to count as a double click.
+    pub double_click_ms: u32,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self {
+            debounce_ms: 20,
+            long_press_ms: 1_000,
+            double_click_ms: 300,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Button {
+    timings: Timings,
+    raw: bool,
+    raw_since_ms: u32,
+    pressed: bool,
+    pressed_at_ms: u32,
+    long_press_reported: bool,
+    double_clicked: bool,
+    click_released_at_ms: Option<u32>,
+}
+
+impl Button {
+    pub fn new(timings: Timings) -> Self {
+        Self {
+            timings,
+            raw: false,
+            raw_since_ms: 0,
+            pressed: false,
+            pressed_at_ms: 0,
+            long_press_reported: false,
+            double_clicked: false,
+            click_released_at_ms: None,
+        }
+    }
+
+    /// Whether the button is pressed, once debounced.
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Take a sample of the raw button state at `now_ms`, which may wrap.
+    /// Samples should keep coming while the button is held, or while a
+    /// change is settling, so that time-based events can be raised.
+    pub fn update(&mut self, now_ms: u32, pressed: bool) -> Option<Event> {
+        if pressed != self.raw {
+            self.raw = pressed;
+            self.raw_since_ms = now_ms;
+        }
+
+        let settled = now_ms.wrapping_sub(self.raw_since_ms) >= self.timings.debounce_ms;
+        if settled && self.raw != self.pressed {
+            self.pressed = self.raw;
+            return Some(if self.pressed {
+                self.press(now_ms)
+            } else {
+                self.release(now_ms)
+            });
+        }
+
+        if self.pressed
+            && !self.long_press_reported
+            && now_ms.wrapping_sub(self.pressed_at_ms) >= self.timings.long_press_ms
+        {
+            self.long_press_reported = true;
+            self.click_released_at_ms = None;
+            return Some(Event::LongPress);
+        }
+
+        None
+    }
+
+    fn press(&mut self, now_ms: u32) -> Event {
+        self.pressed_at_ms = now_ms;
+        self.long_press_reported = false;
+        self.double_clicked = match self.click_released_at_ms.take() {
+            Some(released_at_ms) => {
+                now_ms.wrapping_sub(released_at_ms) <= self.timings.double_click_ms
+            }
+            None => false,
+        };
+        if self.double_clicked {
+            Event::DoubleClick
+        } else {
+            Event::Pressed
+        }
+    }
+
+    fn release(&mut self, now_ms: u32) -> Event {
+        // Only a short, single click can begin a double click
+        self.click_released_at_ms = if self.long_press_reported || self.double_clicked {
+            None
+        } else {
+            Some(now_ms)
+        };
+        Event::Released
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonId {
+    A,
+    B,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PairEvent {
+    Button(ButtonId, Event),
+    /// Both buttons went down within the chord time of each other. Reported
+    /// instead of the second button's press.
+    Chord,
+}
+
+/// Two buttons reporting through one stream of events.
+#[derive(Debug)]
+pub struct Pair {
+    a: Button,
+    b: Button,
+    chord_ms: u32,
+    last_press: Option<(ButtonId, u32)>,
+}
+
+impl Pair {
+    pub fn new(timings: Timings, chord_ms: u32) -> Self {
+        Self {
+            a: Button::new(timings),
+            b: Button::new(timings),
+            chord_ms,
+            last_press: None,
+        }
+    }
+
+    /// Whether a button is pressed, once debounced.
+    pub fn is_pressed(&self, id: ButtonId) -> bool {
+        match id {
+            ButtonId::A => self.a.is_pressed(),
+            ButtonId::B => self.b.is_pressed(),
+        }
+    }
+
+    /// Take a sample of one button's raw state at `now_ms`.
+    pub fn update(&mut self, now_ms: u32, id: ButtonId, pressed: bool) -> Option<PairEvent> {
+        let (button, other) = match id {
+            ButtonId::A => (&mut self.a, &self.b),
+            ButtonId::B => (&mut self.b, &self.a),
+        };
+        let event = button.update(now_ms, pressed)?;
+
+        if let Event::Pressed | Event::DoubleClick = event {
+            match self.last_press.take() {
+                Some((last_id, pressed_at_ms))
+                    if last_id != id
+                        && other.is_pressed()
+                        && now_ms.wrapping_sub(pressed_at_ms) <= self.chord_ms =>
+                {
+                    return Some(PairEvent::Chord);
+                }
+                _ => self.last_press = Some((id, now_ms)),
+            }
+        }
+
+        Some(PairEvent::Button(id, event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Play a trace of (time, raw state) samples, collecting events along
+    /// with when they were raised.
+    fn run(trace: &[(u32, bool)]) -> Vec<(u32, Event)> {
+        let mut button = Button::new(Timings::default());
+        trace
+            .iter()
+            .filter_map(|(now_ms, pressed)| {
+                button
+                    .update(*now_ms, *pressed)
+                    .map(|event| (*now_ms, event))
+            })
+            .collect()
+    }
+
+    /// Samples every `step_ms` from `from_ms` up to but excluding `to_ms`.
+    fn hold(from_ms: u32, to_ms: u32, step_ms: u32, pressed: bool) -> Vec<(u32, bool)> {
+        (from_ms..to_ms)
+            .step_by(step_ms as usize)
+            .map(|now_ms| (now_ms, pressed))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_press_and_release() {
+        let mut trace = hold(0, 100, 5, true);
+        trace.extend(hold(100, 200, 5, false));
+        assert_eq!(
+            run(&trace),
+            vec![(20, Event::Pressed), (120, Event::Released)]
+        );
+    }
+
+    #[test]
+    fn test_bounces_are_ignored() {
+        let trace = [
+            (0, true),
+            (2, false),
+            (4, true),
+            (7, false),
+            (9, true),
+            (20, true),
+            (29, true),
+            (35, true),
+        ];
+        assert_eq!(run(&trace), vec![(29, Event::Pressed)]);
+    }
+
+    #[test]
+    fn test_glitch_shorter_than_debounce_is_ignored() {
+        let trace = [
+            (0, false),
+            (10, true),
+            (15, false),
+            (50, false),
+            (100, false),
+        ];
+        assert_eq!(run(&trace), vec![]);
+    }
+
+    #[test]
+    fn test_long_press_is_reported_once() {
+        let mut trace = hold(0, 2_500, 10, true);
+        trace.extend(hold(2_500, 2_600, 10, false));
+        assert_eq!(
+            run(&trace),
+            vec![
+                (20, Event::Pressed),
+                (1_020, Event::LongPress),
+                (2_520, Event::Released)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_double_click() {
+        let mut trace = hold(0, 100, 10, true);
+        trace.extend(hold(100, 300, 10, false));
+        trace.extend(hold(300, 400, 10, true));
+        trace.extend(hold(400, 500, 10, false));
+        assert_eq!(
+            run(&trace),
+            vec![
+                (20, Event::Pressed),
+                (120, Event::Released),
+                (320, Event::DoubleClick),
+                (420, Event::Released)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slow_second_click_is_a_plain_press() {
+        let mut trace = hold(0, 100, 10, true);
+        trace.extend(hold(100, 600, 10, false));
+        trace.extend(hold(600, 700, 10, true));
+        let events: Vec<Event> = run(&trace).into_iter().map(|(_, event)| event).collect();
+        assert_eq!(
+            events,
+            vec![Event::Pressed, Event::Released, Event::Pressed]
+        );
+    }
+
+    #[test]
+    fn test_triple_click_is_one_double_click() {
+        let mut trace = Vec::new();
+        for click in 0..3 {
+            let start = click * 200;
+            trace.extend(hold(start, start + 100, 10, true));
+            trace.extend(hold(start + 100, start + 200, 10, false));
+        }
+        let events: Vec<Event> = run(&trace).into_iter().map(|(_, event)| event).collect();
+        assert_eq!(
+            events,
+            vec![
+                Event::Pressed,
+                Event::Released,
+                Event::DoubleClick,
+                Event::Released,
+                Event::Pressed,
+                Event::Released
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_press_does_not_start_a_double_click() {
+        let mut trace = hold(0, 1_100, 10, true);
+        trace.extend(hold(1_100, 1_200, 10, false));
+        trace.extend(hold(1_200, 1_300, 10, true));
+        let events: Vec<Event> = run(&trace).into_iter().map(|(_, event)| event).collect();
+        assert_eq!(
+            events,
+            vec![
+                Event::Pressed,
+                Event::LongPress,
+                Event::Released,
+                Event::Pressed
+            ]
+        );
+    }
+
+    /// Play a trace of (time, button, raw state) samples through a pair.
+    fn run_pair(trace: &[(u32, ButtonId, bool)]) -> Vec<PairEvent> {
+        let mut pair = Pair::new(Timings::default(), 100);
+        trace
+            .iter()
+            .filter_map(|(now_ms, id, pressed)| pair.update(*now_ms, *id, *pressed))
+            .collect()
+    }
+
+    /// Sample both buttons every 10ms, with each held between the given times.
+    fn both(a: (u32, u32), b: (u32, u32), to_ms: u32) -> Vec<(u32, ButtonId, bool)> {
+        let mut trace = Vec::new();
+        for now_ms in (0..to_ms).step_by(10) {
+            trace.push((now_ms, ButtonId::A, (a.0..a.1).contains(&now_ms)));
+            trace.push((now_ms, ButtonId::B, (b.0..b.1).contains(&now_ms)));
+        }
+        trace
+    }
+
+    #[test]
+    fn test_pair_reports_which_button() {
+        let events = run_pair(&both((0, 100), (300, 400), 500));
+        assert_eq!(
+            events,
+            vec![
+                PairEvent::Button(ButtonId::A, Event::Pressed),
+                PairEvent::Button(ButtonId::A, Event::Released),
+                PairEvent::Button(ButtonId::B, Event::Pressed),
+                PairEvent::Button(ButtonId::B, Event::Released),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_close_presses_are_a_chord() {
+        let events = run_pair(&both((0, 200), (50, 250), 400));
+        assert_eq!(
+            events,
+            vec![
+                PairEvent::Button(ButtonId::A, Event::Pressed),
+                PairEvent::Chord,
+                PairEvent::Button(ButtonId::A, Event::Released),
+                PairEvent::Button(ButtonId::B, Event::Released),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_distant_presses_are_not_a_chord() {
+        let events = run_pair(&both((0, 400), (200, 300), 500));
+        assert_eq!(
+            events,
+            vec![
+                PairEvent::Button(ButtonId::A, Event::Pressed),
+                PairEvent::Button(ButtonId::B, Event::Pressed),
+                PairEvent::Button(ButtonId::B, Event::Released),
+                PairEvent::Button(ButtonId::A, Event::Released),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_released_first_button_is_not_a_chord() {
+        let events = run_pair(&both((0, 40), (80, 200), 300));
+        assert!(!events.contains(&PairEvent::Chord));
+    }
+
+    #[test]
+    fn test_clock_wrap() {
+        let start = u32::MAX - 10;
+        let trace: Vec<(u32, bool)> = (0..40)
+            .map(|step| (start.wrapping_add(step), true))
+            .collect();
+        assert_eq!(run(&trace), vec![(start.wrapping_add(20), Event::Pressed)]);
+    }
+}
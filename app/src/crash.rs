@@ -0,0 +1,237 @@
+//! A record of a panic that can outlive a reset, for the firmware to keep
+//! in RAM that startup leaves alone and report on the next boot.
+//!
+//! RAM that has never been written holds whatever it powered up with, so a
+//! record only counts once its magic number and checksum both agree.
+
+use core::fmt::{self, Write};
+use core::str;
+
+/// Marks a record as written by `CrashRecord::record`.
+pub const MAGIC: u32 = 0x5041_4e43;
+
+/// The most of a panic's file name that is kept.
+pub const FILE_LEN: usize = 64;
+
+/// The most of a panic's message that is kept.
+pub const MESSAGE_LEN: usize = 128;
+
+#[repr(C)]
+pub struct CrashRecord {
+    magic: u32,
+    line: u32,
+    column: u32,
+    file_len: u32,
+    message_len: u32,
+    file: [u8; FILE_LEN],
+    message: [u8; MESSAGE_LEN],
+    checksum: u32,
+}
+
+/// What a valid record says.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crash<'a> {
+    pub file: &'a str,
+    pub line: u32,
+    pub column: u32,
+    pub message: &'a str,
+}
+
+impl<'a> fmt::Display for Crash<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "panicked at '{}', {}:{}:{}",
+            self.message, self.file, self.line, self.column
+        )
+    }
+}
+
+impl CrashRecord {
+    /// A record that holds no crash.
+    pub const fn new() -> Self {
+        Self {
+            magic: 0,
+            line: 0,
+            column: 0,
+            file_len: 0,
+            message_len: 0,
+            file: [0; FILE_LEN],
+            message: [0; MESSAGE_LEN],
+            checksum: 0,
+        }
+    }
+
+    /// Keep a panic's location and message, cutting either short where it
+    /// won't fit.
+    pub fn record(&mut self, file: &str, line: u32, column: u32, message: fmt::Arguments) {
+        let file = truncate(file, FILE_LEN);
+        self.file[..file.len()].copy_from_slice(file.as_bytes());
+        self.file_len = file.len() as u32;
+        self.line = line;
+        self.column = column;
+
+        let mut writer = Truncating {
+            buffer: &mut self.message,
+            len: 0,
+        };
+        let _ = writer.write_fmt(message);
+        self.message_len = writer.len as u32;
+
+        self.magic = MAGIC;
+        self.checksum = self.checksum();
+    }
+
+    /// The crash recorded, if the record can be trusted.
+    pub fn crash(&self) -> Option<Crash<'_>> {
+        if self.magic != MAGIC || self.checksum != self.checksum() {
+            return None;
+        }
+        let file = self.file.get(..self.file_len as usize)?;
+        let message = self.message.get(..self.message_len as usize)?;
+        Some(Crash {
+            file: str::from_utf8(file).ok()?,
+            line: self.line,
+            column: self.column,
+            message: str::from_utf8(message).ok()?,
+        })
+    }
+
+    /// Forget the crash, so it is only reported once.
+    pub fn clear(&mut self) {
+        self.magic = 0;
+    }
+
+    /// Covers every field but the checksum itself.
+    fn checksum(&self) -> u32 {
+        let fields = [
+            self.magic,
+            self.line,
+            self.column,
+            self.file_len,
+            self.message_len,
+        ];
+        fnv1a(
+            fields
+                .iter()
+                .flat_map(|field| field.to_le_bytes())
+                .chain(self.file.iter().copied())
+                .chain(self.message.iter().copied()),
+        )
+    }
+}
+
+/// The 32 bit FNV-1a hash, for checking records kept across a reset.
+pub(crate) fn fnv1a(bytes: impl Iterator<Item = u8>) -> u32 {
+    bytes.fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+impl Default for CrashRecord {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The longest start of `s` that fits in `len` bytes without splitting a
+/// character.
+fn truncate(s: &str, len: usize) -> &str {
+    if s.len() <= len {
+        return s;
+    }
+    let mut end = len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Writes as much as fits, silently dropping the rest.
+struct Truncating<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Write for Truncating<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let s = truncate(s, self.buffer.len() - self.len);
+        self.buffer[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_read_back() {
+        let mut record = CrashRecord::new();
+        assert_eq!(record.crash(), None);
+
+        record.record(
+            "src/main.rs",
+            42,
+            5,
+            format_args!("index {} out of range", 7),
+        );
+        let crash = record.crash().unwrap();
+        assert_eq!(
+            crash,
+            Crash {
+                file: "src/main.rs",
+                line: 42,
+                column: 5,
+                message: "index 7 out of range",
+            }
+        );
+        assert_eq!(
+            crash.to_string(),
+            "panicked at 'index 7 out of range', src/main.rs:42:5"
+        );
+
+        record.clear();
+        assert_eq!(record.crash(), None);
+    }
+
+    #[test]
+    fn test_long_text_is_cut_at_a_character() {
+        let mut record = CrashRecord::new();
+        let file = "é".repeat(FILE_LEN);
+        let message = "ü".repeat(MESSAGE_LEN);
+        record.record(&file, 1, 1, format_args!("a{}", message));
+        let crash = record.crash().unwrap();
+        assert_eq!(crash.file, "é".repeat(FILE_LEN / 2));
+        assert_eq!(crash.message.len(), MESSAGE_LEN - 1);
+        assert!(crash.message.starts_with("aü"));
+    }
+
+    #[test]
+    fn test_damage_is_detected() {
+        let mut record = CrashRecord::new();
+        record.record("src/main.rs", 42, 5, format_args!("oops"));
+
+        record.line = 43;
+        assert_eq!(record.crash(), None);
+        record.line = 42;
+        assert!(record.crash().is_some());
+
+        record.message[MESSAGE_LEN - 1] ^= 1;
+        assert_eq!(record.crash(), None);
+        record.message[MESSAGE_LEN - 1] ^= 1;
+
+        record.magic ^= 1;
+        assert_eq!(record.crash(), None);
+    }
+
+    #[test]
+    fn test_lengths_are_checked_even_with_a_good_checksum() {
+        let mut record = CrashRecord::new();
+        record.record("src/main.rs", 42, 5, format_args!("oops"));
+        record.message_len = MESSAGE_LEN as u32 + 1;
+        record.checksum = record.checksum();
+        assert_eq!(record.crash(), None);
+    }
+}
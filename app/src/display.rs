@@ -0,0 +1,448 @@
+//! Row scanning for the micro:bit's 5x5 LED matrix.
+//!
+//! The matrix is wired so that only one row can be driven at a time, though
+//! how its rows and columns map onto the image depends on the board, as
+//! described by a `matrix::Layout`. Rows are active high and columns are
+//! active low, so a pixel lights when its row pin is high and its column
+//! pin is low. Refreshing one row per timer tick, fast enough, gives the
+//! appearance of a steady image.
+//!
+//! Greyscale is produced by switching each column off part way through its
+//! row's period, so dimmer pixels spend less of the period lit.
+
+use crate::matrix::{self, Layout};
+use core::fmt;
+use core::str::FromStr;
+
+pub const ROWS: usize = 5;
+pub const COLS: usize = 5;
+
+/// The brightest a pixel can be. As with MicroPython, pixel levels run from
+/// 0 (off) to 9.
+pub const MAX_LEVEL: u8 = 9;
+
+/// The global brightness at which pixels are shown at their full level.
+pub const MAX_BRIGHTNESS: u8 = 255;
+
+/// Per-mille of a row period that each pixel level is lit for, following a
+/// gamma of 2.2 so that the steps look evenly spaced to the eye.
+pub const GAMMA: [u16; MAX_LEVEL as usize + 1] = [0, 8, 37, 89, 168, 274, 410, 575, 772, 1000];
+
+/// A 5x5 image holding a level from 0 to `MAX_LEVEL` for each pixel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Image([[u8; COLS]; ROWS]);
+
+impl Image {
+    /// Levels above `MAX_LEVEL` are shown at `MAX_LEVEL`.
+    pub const fn new(pixels: [[u8; COLS]; ROWS]) -> Self {
+        Self(pixels)
+    }
+
+    pub const fn blank() -> Self {
+        Self([[0; COLS]; ROWS])
+    }
+
+    /// An image from five rows of bits, top first, with the leftmost column
+    /// held in bit 4 of each row. Set bits are shown at `level`.
+    pub const fn from_bits(bits: &[u8; ROWS], level: u8) -> Self {
+        let mut pixels = [[0; COLS]; ROWS];
+        let mut row = 0;
+        while row < ROWS {
+            let mut col = 0;
+            while col < COLS {
+                if bits[row] & (1 << (COLS - 1 - col)) != 0 {
+                    pixels[row][col] = level;
+                }
+                col += 1;
+            }
+            row += 1;
+        }
+        Self(pixels)
+    }
+
+    pub fn pixel(&self, row: usize, col: usize) -> u8 {
+        self.0[row][col]
+    }
+
+    pub fn set_pixel(&mut self, row: usize, col: usize, value: u8) {
+        self.0[row][col] = value.min(MAX_LEVEL);
+    }
+
+    pub fn pixels(&self) -> &[[u8; COLS]; ROWS] {
+        &self.0
+    }
+
+    /// An image from five rows of text, top first, such as `"09990"`. Each
+    /// character is a pixel's level, with `.` also meaning off and `#`
+    /// meaning `MAX_LEVEL`.
+    pub fn from_rows<'a>(rows: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut pixels = [[0; COLS]; ROWS];
+        let mut rows = rows.into_iter();
+        for row in pixels.iter_mut() {
+            let text = rows.next()?;
+            if text.chars().count() != COLS {
+                return None;
+            }
+            for (pixel, c) in row.iter_mut().zip(text.chars()) {
+                *pixel = match c {
+                    '.' => 0,
+                    '#' => MAX_LEVEL,
+                    c => c.to_digit(10)? as u8,
+                };
+            }
+        }
+        match rows.next() {
+            Some(_) => None,
+            None => Some(Self(pixels)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseImageError;
+
+/// Parses whitespace separated rows, as taken by `Image::from_rows`.
+impl FromStr for Image {
+    type Err = ParseImageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_rows(s.split_whitespace()).ok_or(ParseImageError)
+    }
+}
+
+/// Shows the rows as digits, separated by spaces, such that the text
+/// parses back to the same image.
+impl fmt::Display for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, row) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" ")?;
+            }
+            for level in row.iter() {
+                write!(f, "{}", level.min(&MAX_LEVEL))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How long a pixel at `level` should be lit within a row period of
+/// `period_us`, once scaled by the global `brightness`.
+pub fn on_time_us(level: u8, brightness: u8, period_us: u32) -> u32 {
+    let gamma = u64::from(GAMMA[usize::from(level.min(MAX_LEVEL))]);
+    let scaled =
+        u64::from(period_us) * gamma * u64::from(brightness) / (1000 * u64::from(MAX_BRIGHTNESS));
+    scaled as u32
+}
+
+/// The pin levels to apply for one step of the scan, `true` being high,
+/// along with how long to wait before taking the next step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RowDrive<const R: usize = ROWS, const C: usize = COLS> {
+    pub row: usize,
+    pub rows: [bool; R],
+    pub cols: [bool; C],
+    pub next_us: u32,
+}
+
+/// Walks the rows of a matrix in order, wrapping back to the top after
+/// the last row. Each row takes one or more steps: the first lights every
+/// pixel with a non-zero on-time, and each further step switches off the
+/// pixels whose on-time has elapsed.
+#[derive(Debug)]
+pub struct Scanner<const R: usize = ROWS, const C: usize = COLS> {
+    layout: &'static Layout<R, C>,
+    row: usize,
+    elapsed_us: u32,
+    on_times_us: [u32; C],
+}
+
+impl Scanner {
+    /// A scanner for the v2's matrix, which is wired as it looks.
+    pub const fn new() -> Self {
+        Self::with_layout(&matrix::V2)
+    }
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const R: usize, const C: usize> Scanner<R, C> {
+    pub const fn with_layout(layout: &'static Layout<R, C>) -> Self {
+        Self {
+            layout,
+            row: 0,
+            elapsed_us: 0,
+            on_times_us: [0; C],
+        }
+    }
+
+    /// Whether the next step starts a new frame. This is the moment to swap
+    /// in a new image without tearing.
+    pub fn is_frame_start(&self) -> bool {
+        self.row == 0 && self.elapsed_us == 0
+    }
+
+    /// Produce the pin levels for the next step of the scan and advance.
+    pub fn step(&mut self, image: &Image, brightness: u8, period_us: u32) -> RowDrive<R, C> {
+        let row = self.row;
+
+        if self.elapsed_us == 0 {
+            let pixels = self.layout.pixels[row].iter();
+            for (time_us, pixel) in self.on_times_us.iter_mut().zip(pixels) {
+                *time_us = pixel.map_or(0, |(row, col)| {
+                    on_time_us(image.pixel(row, col), brightness, period_us)
+                });
+            }
+        }
+
+        let mut rows = [false; R];
+        rows[row] = true;
+
+        let mut cols = [true; C];
+        for (level, on_time_us) in cols.iter_mut().zip(self.on_times_us.iter()) {
+            *level = *on_time_us <= self.elapsed_us;
+        }
+
+        let next_deadline_us = self
+            .on_times_us
+            .iter()
+            .copied()
+            .filter(|on_time_us| *on_time_us > self.elapsed_us && *on_time_us < period_us)
+            .min()
+            .unwrap_or(period_us);
+        let next_us = next_deadline_us - self.elapsed_us;
+
+        if next_deadline_us >= period_us {
+            self.row = (row + 1) % R;
+            self.elapsed_us = 0;
+        } else {
+            self.elapsed_us = next_deadline_us;
+        }
+
+        RowDrive {
+            row,
+            rows,
+            cols,
+            next_us,
+        }
+    }
+}
+
+/// The time each row should be lit for, in microseconds, so that the
+/// whole matrix is refreshed at the given rate.
+pub const fn row_period_us(frame_rate_hz: u32) -> u32 {
+    1_000_000 / (frame_rate_hz * ROWS as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD_US: u32 = 2_000;
+
+    fn full(level: u8) -> Image {
+        Image::new([[level; COLS]; ROWS])
+    }
+
+    #[test]
+    fn test_scan_order_wraps() {
+        let image = full(MAX_LEVEL);
+        let mut scanner = Scanner::new();
+        let rows: Vec<usize> = (0..ROWS * 2)
+            .map(|_| scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US).row)
+            .collect();
+        assert_eq!(rows, vec![0, 1, 2, 3, 4, 0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_frame_start_is_only_before_the_first_step() {
+        let mut image = Image::blank();
+        image.set_pixel(0, 0, 5);
+        let mut scanner = Scanner::new();
+        let frame_starts: Vec<bool> = (0..ROWS + 2)
+            .map(|_| {
+                let frame_start = scanner.is_frame_start();
+                scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US);
+                frame_start
+            })
+            .collect();
+        assert_eq!(
+            frame_starts,
+            vec![true, false, false, false, false, false, true]
+        );
+    }
+
+    #[test]
+    fn test_only_the_scanned_row_is_high() {
+        let image = Image::blank();
+        let mut scanner = Scanner::new();
+        for row in 0..ROWS {
+            let drive = scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US);
+            for (r, level) in drive.rows.iter().enumerate() {
+                assert_eq!(*level, r == row);
+            }
+            assert_eq!(drive.next_us, PERIOD_US);
+        }
+    }
+
+    #[test]
+    fn test_lit_pixels_pull_columns_low() {
+        let image = Image::new([
+            [9, 9, 9, 0, 0],
+            [0, 9, 0, 9, 9],
+            [0, 9, 9, 0, 0],
+            [0, 9, 9, 0, 0],
+            [0, 0, 0, 9, 9],
+        ]);
+        let mut scanner = Scanner::new();
+        assert_eq!(
+            scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US).cols,
+            [false, false, false, true, true]
+        );
+        assert_eq!(
+            scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US).cols,
+            [true, false, true, false, false]
+        );
+    }
+
+    #[test]
+    fn test_dim_pixels_switch_off_early() {
+        let mut image = Image::blank();
+        image.set_pixel(0, 0, 9);
+        image.set_pixel(0, 1, 5);
+        image.set_pixel(0, 2, 1);
+        let mut scanner = Scanner::new();
+
+        let drive = scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US);
+        assert_eq!(drive.row, 0);
+        assert_eq!(drive.cols, [false, false, false, true, true]);
+        assert_eq!(drive.next_us, 16);
+
+        let drive = scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US);
+        assert_eq!(drive.row, 0);
+        assert_eq!(drive.cols, [false, false, true, true, true]);
+        assert_eq!(drive.next_us, 548 - 16);
+
+        let drive = scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US);
+        assert_eq!(drive.row, 0);
+        assert_eq!(drive.cols, [false, true, true, true, true]);
+        assert_eq!(drive.next_us, PERIOD_US - 548);
+
+        assert_eq!(scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US).row, 1);
+    }
+
+    #[test]
+    fn test_v1_rows_light_pixels_across_the_image() {
+        // The v1's second row pin lights the middle of the image, and two
+        // of the top row's pixels
+        let image = Image::new([
+            [0, 9, 0, 9, 0],
+            [0, 0, 0, 0, 0],
+            [0, 0, 9, 0, 0],
+            [0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0],
+        ]);
+        let mut scanner = Scanner::with_layout(&matrix::V1);
+        let drive = scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US);
+        assert_eq!(drive.rows, [true, false, false]);
+        assert_eq!(drive.cols, [true; matrix::V1_COLS]);
+
+        let drive = scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US);
+        assert_eq!(drive.rows, [false, true, false]);
+        assert_eq!(
+            drive.cols,
+            [true, true, false, false, false, true, true, true, true]
+        );
+
+        scanner.step(&image, MAX_BRIGHTNESS, PERIOD_US);
+        assert!(scanner.is_frame_start());
+    }
+
+    #[test]
+    fn test_on_time_follows_gamma() {
+        assert_eq!(on_time_us(0, MAX_BRIGHTNESS, PERIOD_US), 0);
+        assert_eq!(on_time_us(MAX_LEVEL, MAX_BRIGHTNESS, PERIOD_US), PERIOD_US);
+        let on_times: Vec<u32> = (0..=MAX_LEVEL)
+            .map(|level| on_time_us(level, MAX_BRIGHTNESS, PERIOD_US))
+            .collect();
+        assert!(on_times.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_on_time_scales_with_brightness() {
+        assert_eq!(on_time_us(MAX_LEVEL, 0, PERIOD_US), 0);
+        assert_eq!(on_time_us(MAX_LEVEL, 51, PERIOD_US), PERIOD_US / 5);
+        assert_eq!(
+            on_time_us(MAX_LEVEL + 1, MAX_BRIGHTNESS, PERIOD_US),
+            PERIOD_US
+        );
+    }
+
+    #[test]
+    fn test_from_bits() {
+        let bits = [0b10000, 0b01000, 0b00100, 0b00010, 0b00001];
+        assert_eq!(
+            Image::from_bits(&bits, 4),
+            Image::new([
+                [4, 0, 0, 0, 0],
+                [0, 4, 0, 0, 0],
+                [0, 0, 4, 0, 0],
+                [0, 0, 0, 4, 0],
+                [0, 0, 0, 0, 4],
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let image: Image = ".#9#.\n01234\n56789\n.....\n#####\n".parse().unwrap();
+        assert_eq!(
+            image,
+            Image::new([
+                [0, 9, 9, 9, 0],
+                [0, 1, 2, 3, 4],
+                [5, 6, 7, 8, 9],
+                [0, 0, 0, 0, 0],
+                [9, 9, 9, 9, 9],
+            ])
+        );
+        assert_eq!(image.to_string(), "09990 01234 56789 00000 99999");
+        assert_eq!(image.to_string().parse(), Ok(image));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_pictures() {
+        assert_eq!(
+            "00000 00000 00000 00000".parse::<Image>(),
+            Err(ParseImageError)
+        );
+        assert_eq!(
+            "00000 00000 00000 00000 00000 00000".parse::<Image>(),
+            Err(ParseImageError)
+        );
+        assert_eq!(
+            "00000 00000 0000 00000 00000".parse::<Image>(),
+            Err(ParseImageError)
+        );
+        assert_eq!(
+            "00000 00000 0x000 00000 00000".parse::<Image>(),
+            Err(ParseImageError)
+        );
+    }
+
+    #[test]
+    fn test_set_pixel_clamps_level() {
+        let mut image = Image::blank();
+        image.set_pixel(2, 2, 200);
+        assert_eq!(image.pixel(2, 2), MAX_LEVEL);
+    }
+
+    #[test]
+    fn test_row_period() {
+        assert_eq!(row_period_us(100), 2_000);
+    }
+}
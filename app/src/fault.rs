@@ -0,0 +1,512 @@
+//! Makes sense of a HardFault from the registers a Cortex-M leaves behind,
+//! and keeps it in a record that can outlive a reset, as `crash` does for
+//! panics.
+//!
+//! The configurable fault status register (CFSR) packs the memory
+//! management, bus and usage fault status registers into one word. Faults
+//! that can't be handled by their own handler escalate to a HardFault, which
+//! the HardFault status register (HFSR) then says.
+
+use crate::crash::fnv1a;
+use core::fmt;
+
+/// The registers pushed to the stack on taking an exception, in the order
+/// they are pushed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExceptionFrame {
+    pub r0: u32,
+    pub r1: u32,
+    pub r2: u32,
+    pub r3: u32,
+    pub r12: u32,
+    pub lr: u32,
+    pub pc: u32,
+    pub xpsr: u32,
+}
+
+/// Set in the stacked xPSR when a word of padding was pushed above the
+/// frame to align it to 8 bytes.
+const XPSR_PADDED: u32 = 1 << 9;
+/// The words of an `ExceptionFrame`.
+const BASIC_FRAME_WORDS: usize = 8;
+/// S0-S15, FPSCR and a reserved word, stacked above the basic frame when
+/// the context had used the FPU.
+const FP_FRAME_WORDS: usize = 18;
+
+impl ExceptionFrame {
+    /// Whether a word of padding was pushed above the frame.
+    pub fn is_padded(&self) -> bool {
+        self.xpsr & XPSR_PADDED != 0
+    }
+
+    /// How many words the processor pushed for this frame, from its start
+    /// to where the stack was before the exception, given whether room was
+    /// made for the FPU's registers.
+    pub fn stacked_words(&self, fp_registers: bool) -> usize {
+        let fp_words = if fp_registers { FP_FRAME_WORDS } else { 0 };
+        BASIC_FRAME_WORDS + fp_words + self.is_padded() as usize
+    }
+}
+
+/// The fault status and address registers of the system control block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaultStatus {
+    pub cfsr: u32,
+    pub hfsr: u32,
+    pub mmfar: u32,
+    pub bfar: u32,
+}
+
+/// Something that went wrong, as flagged by a bit of the CFSR or HFSR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cause {
+    InstructionAccessViolation,
+    DataAccessViolation,
+    MemManageOnUnstacking,
+    MemManageOnStacking,
+    MemManageOnFpLazyState,
+    InstructionBusError,
+    PreciseDataBusError,
+    ImpreciseDataBusError,
+    BusFaultOnUnstacking,
+    BusFaultOnStacking,
+    BusFaultOnFpLazyState,
+    UndefinedInstruction,
+    InvalidState,
+    InvalidPcLoad,
+    NoCoprocessor,
+    UnalignedAccess,
+    DivideByZero,
+    VectorTableRead,
+    Forced,
+    DebugEvent,
+}
+
+/// Each cause with the CFSR bit that flags it.
+const CFSR_CAUSES: [(u32, Cause); 17] = [
+    (0, Cause::InstructionAccessViolation),
+    (1, Cause::DataAccessViolation),
+    (3, Cause::MemManageOnUnstacking),
+    (4, Cause::MemManageOnStacking),
+    (5, Cause::MemManageOnFpLazyState),
+    (8, Cause::InstructionBusError),
+    (9, Cause::PreciseDataBusError),
+    (10, Cause::ImpreciseDataBusError),
+    (11, Cause::BusFaultOnUnstacking),
+    (12, Cause::BusFaultOnStacking),
+    (13, Cause::BusFaultOnFpLazyState),
+    (16, Cause::UndefinedInstruction),
+    (17, Cause::InvalidState),
+    (18, Cause::InvalidPcLoad),
+    (19, Cause::NoCoprocessor),
+    (24, Cause::UnalignedAccess),
+    (25, Cause::DivideByZero),
+];
+
+/// Each cause with the HFSR bit that flags it.
+const HFSR_CAUSES: [(u32, Cause); 3] = [
+    (1, Cause::VectorTableRead),
+    (30, Cause::Forced),
+    (31, Cause::DebugEvent),
+];
+
+const MMARVALID: u32 = 1 << 7;
+const BFARVALID: u32 = 1 << 15;
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Cause::InstructionAccessViolation => {
+                "instruction fetched from a region that doesn't allow it"
+            }
+            Cause::DataAccessViolation => "data accessed in a region that doesn't allow it",
+            Cause::MemManageOnUnstacking => "memory management fault returning from an exception",
+            Cause::MemManageOnStacking => "memory management fault entering an exception",
+            Cause::MemManageOnFpLazyState => "memory management fault saving floating point state",
+            Cause::InstructionBusError => "bus error fetching an instruction",
+            Cause::PreciseDataBusError => "bus error accessing data",
+            Cause::ImpreciseDataBusError => "bus error accessing data, some time before",
+            Cause::BusFaultOnUnstacking => "bus fault returning from an exception",
+            Cause::BusFaultOnStacking => "bus fault entering an exception",
+            Cause::BusFaultOnFpLazyState => "bus fault saving floating point state",
+            Cause::UndefinedInstruction => "undefined instruction",
+            Cause::InvalidState => "instruction run in an invalid state, such as ARM mode",
+            Cause::InvalidPcLoad => "invalid return to the program counter",
+            Cause::NoCoprocessor => "coprocessor used while disabled or absent",
+            Cause::UnalignedAccess => "unaligned memory access",
+            Cause::DivideByZero => "divide by zero",
+            Cause::VectorTableRead => "bus fault reading the vector table",
+            Cause::Forced => "escalated from a fault that couldn't be handled",
+            Cause::DebugEvent => "debug event with no debugger to take it",
+        })
+    }
+}
+
+impl FaultStatus {
+    /// Every cause flagged, fault status bits first.
+    pub fn causes(&self) -> impl Iterator<Item = Cause> {
+        let flagged = |register: u32| {
+            move |(bit, cause): &(u32, Cause)| {
+                if register & (1 << bit) != 0 {
+                    Some(*cause)
+                } else {
+                    None
+                }
+            }
+        };
+        CFSR_CAUSES
+            .iter()
+            .filter_map(flagged(self.cfsr))
+            .chain(HFSR_CAUSES.iter().filter_map(flagged(self.hfsr)))
+    }
+
+    /// The address a memory management fault came from, if known.
+    pub fn memory_address(&self) -> Option<u32> {
+        if self.cfsr & MMARVALID != 0 {
+            Some(self.mmfar)
+        } else {
+            None
+        }
+    }
+
+    /// The address a bus fault came from, if known.
+    pub fn bus_address(&self) -> Option<u32> {
+        if self.cfsr & BFARVALID != 0 {
+            Some(self.bfar)
+        } else {
+            None
+        }
+    }
+}
+
+/// The most words of the stack kept from above the exception frame.
+pub const STACK_WORDS: usize = 8;
+
+/// All that is known about a HardFault.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fault {
+    pub frame: ExceptionFrame,
+    /// Whether room for the FPU's registers was stacked with the frame.
+    pub fp_registers: bool,
+    pub status: FaultStatus,
+    /// The stack as it was before the exception, past any FPU registers
+    /// and padding stacked with the frame, the most recent first.
+    pub stack: [u32; STACK_WORDS],
+    pub stack_len: usize,
+}
+
+impl Fault {
+    /// Describe the fault a line at a time, without line endings.
+    pub fn report(&self, mut line: impl FnMut(fmt::Arguments)) {
+        let frame = &self.frame;
+        let status = &self.status;
+        line(format_args!(
+            "HardFault at pc {:#010x}, lr {:#010x}, xpsr {:#010x}",
+            frame.pc, frame.lr, frame.xpsr
+        ));
+        line(format_args!(
+            "r0 {:#010x}, r1 {:#010x}, r2 {:#010x}, r3 {:#010x}, r12 {:#010x}",
+            frame.r0, frame.r1, frame.r2, frame.r3, frame.r12
+        ));
+        let fp_registers = if self.fp_registers {
+            "with FPU registers"
+        } else {
+            "without FPU registers"
+        };
+        let padding = if frame.is_padded() {
+            ", padded to 8 bytes"
+        } else {
+            ""
+        };
+        line(format_args!("frame stacked {}{}", fp_registers, padding));
+        line(format_args!(
+            "cfsr {:#010x}, hfsr {:#010x}, mmfar {:#010x}, bfar {:#010x}",
+            status.cfsr, status.hfsr, status.mmfar, status.bfar
+        ));
+        for cause in status.causes() {
+            line(format_args!("cause: {}", cause));
+        }
+        if let Some(address) = status.memory_address() {
+            line(format_args!("memory fault address: {:#010x}", address));
+        }
+        if let Some(address) = status.bus_address() {
+            line(format_args!("bus fault address: {:#010x}", address));
+        }
+        line(format_args!(
+            "stack: {}",
+            Words(&self.stack[..self.stack_len.min(STACK_WORDS)])
+        ));
+    }
+
+    fn to_words(self) -> [u32; RECORD_WORDS] {
+        let frame = &self.frame;
+        let status = &self.status;
+        let mut words = [0; RECORD_WORDS];
+        words[..14].copy_from_slice(&[
+            frame.r0,
+            frame.r1,
+            frame.r2,
+            frame.r3,
+            frame.r12,
+            frame.lr,
+            frame.pc,
+            frame.xpsr,
+            status.cfsr,
+            status.hfsr,
+            status.mmfar,
+            status.bfar,
+            self.fp_registers as u32,
+            self.stack_len as u32,
+        ]);
+        words[14..].copy_from_slice(&self.stack);
+        words
+    }
+
+    fn from_words(words: &[u32; RECORD_WORDS]) -> Option<Self> {
+        let stack_len = words[13] as usize;
+        if stack_len > STACK_WORDS {
+            return None;
+        }
+        let mut stack = [0; STACK_WORDS];
+        stack.copy_from_slice(&words[14..]);
+        Some(Self {
+            frame: ExceptionFrame {
+                r0: words[0],
+                r1: words[1],
+                r2: words[2],
+                r3: words[3],
+                r12: words[4],
+                lr: words[5],
+                pc: words[6],
+                xpsr: words[7],
+            },
+            fp_registers: words[12] != 0,
+            status: FaultStatus {
+                cfsr: words[8],
+                hfsr: words[9],
+                mmfar: words[10],
+                bfar: words[11],
+            },
+            stack,
+            stack_len,
+        })
+    }
+}
+
+/// Shows words in hex, separated by spaces.
+struct Words<'a>(&'a [u32]);
+
+impl<'a> fmt::Display for Words<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, word) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:08x}", word)?;
+        }
+        Ok(())
+    }
+}
+
+/// Marks a record as written by `FaultRecord::record`.
+pub const MAGIC: u32 = 0x4841_5244;
+
+const RECORD_WORDS: usize = 14 + STACK_WORDS;
+
+/// A fault kept in RAM across a reset, trusted only if its magic number and
+/// checksum agree.
+#[repr(C)]
+pub struct FaultRecord {
+    magic: u32,
+    words: [u32; RECORD_WORDS],
+    checksum: u32,
+}
+
+impl FaultRecord {
+    /// A record that holds no fault.
+    pub const fn new() -> Self {
+        Self {
+            magic: 0,
+            words: [0; RECORD_WORDS],
+            checksum: 0,
+        }
+    }
+
+    pub fn record(&mut self, fault: &Fault) {
+        self.words = fault.to_words();
+        self.magic = MAGIC;
+        self.checksum = self.checksum();
+    }
+
+    /// The fault recorded, if the record can be trusted.
+    pub fn fault(&self) -> Option<Fault> {
+        if self.magic != MAGIC || self.checksum != self.checksum() {
+            return None;
+        }
+        Fault::from_words(&self.words)
+    }
+
+    /// Forget the fault, so it is only reported once.
+    pub fn clear(&mut self) {
+        self.magic = 0;
+    }
+
+    /// Covers every field but the checksum itself.
+    fn checksum(&self) -> u32 {
+        fnv1a(
+            core::iter::once(&self.magic)
+                .chain(self.words.iter())
+                .flat_map(|word| word.to_le_bytes()),
+        )
+    }
+}
+
+impl Default for FaultRecord {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fault() -> Fault {
+        Fault {
+            frame: ExceptionFrame {
+                r0: 0,
+                r1: 1,
+                r2: 2,
+                r3: 3,
+                r12: 12,
+                lr: 0x0000_1235,
+                pc: 0x0000_2468,
+                xpsr: 0x6100_0000,
+            },
+            fp_registers: false,
+            status: FaultStatus {
+                // PRECISERR with BFARVALID, escalated
+                cfsr: 0x0000_8200,
+                hfsr: 0x4000_0000,
+                mmfar: 0xe000_ed34,
+                bfar: 0x1000_0000,
+            },
+            stack: [0xaa, 0xbb, 0, 0, 0, 0, 0, 0],
+            stack_len: 2,
+        }
+    }
+
+    #[test]
+    fn test_causes() {
+        let status = FaultStatus {
+            cfsr: 0x0200_0082,
+            hfsr: 0x4000_0002,
+            mmfar: 0x2000_0000,
+            bfar: 0,
+        };
+        let causes: Vec<Cause> = status.causes().collect();
+        assert_eq!(
+            causes,
+            [
+                Cause::DataAccessViolation,
+                Cause::DivideByZero,
+                Cause::VectorTableRead,
+                Cause::Forced
+            ]
+        );
+        assert_eq!(status.memory_address(), Some(0x2000_0000));
+        assert_eq!(status.bus_address(), None);
+
+        assert_eq!(FaultStatus::default().causes().count(), 0);
+    }
+
+    #[test]
+    fn test_every_cause_has_its_own_bit() {
+        for (bit, cause) in CFSR_CAUSES.iter() {
+            let status = FaultStatus {
+                cfsr: 1 << bit,
+                ..FaultStatus::default()
+            };
+            assert_eq!(status.causes().collect::<Vec<_>>(), [*cause]);
+        }
+        for (bit, cause) in HFSR_CAUSES.iter() {
+            let status = FaultStatus {
+                hfsr: 1 << bit,
+                ..FaultStatus::default()
+            };
+            assert_eq!(status.causes().collect::<Vec<_>>(), [*cause]);
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let mut lines = Vec::new();
+        fault().report(|line| lines.push(line.to_string()));
+        assert_eq!(
+            lines,
+            [
+                "HardFault at pc 0x00002468, lr 0x00001235, xpsr 0x61000000",
+                "r0 0x00000000, r1 0x00000001, r2 0x00000002, r3 0x00000003, r12 0x0000000c",
+                "frame stacked without FPU registers",
+                "cfsr 0x00008200, hfsr 0x40000000, mmfar 0xe000ed34, bfar 0x10000000",
+                "cause: bus error accessing data",
+                "cause: escalated from a fault that couldn't be handled",
+                "bus fault address: 0x10000000",
+                "stack: 000000aa 000000bb",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_and_read_back() {
+        let mut record = FaultRecord::new();
+        assert_eq!(record.fault(), None);
+
+        record.record(&fault());
+        assert_eq!(record.fault(), Some(fault()));
+
+        record.words[6] ^= 1;
+        assert_eq!(record.fault(), None);
+        record.words[6] ^= 1;
+        assert_eq!(record.fault(), Some(fault()));
+
+        record.clear();
+        assert_eq!(record.fault(), None);
+    }
+
+    #[test]
+    fn test_stack_len_is_checked_even_with_a_good_checksum() {
+        let mut record = FaultRecord::new();
+        record.record(&fault());
+        record.words[13] = STACK_WORDS as u32 + 1;
+        record.checksum = record.checksum();
+        assert_eq!(record.fault(), None);
+    }
+
+    #[test]
+    fn test_stacked_words() {
+        let basic = fault().frame;
+        assert_eq!(basic.stacked_words(false), 8);
+        assert_eq!(basic.stacked_words(true), 26);
+
+        let padded = ExceptionFrame {
+            xpsr: basic.xpsr | 1 << 9,
+            ..basic
+        };
+        assert_eq!(padded.stacked_words(false), 9);
+        assert_eq!(padded.stacked_words(true), 27);
+    }
+
+    #[test]
+    fn test_report_says_what_was_stacked_with_the_frame() {
+        let mut fault = fault();
+        fault.fp_registers = true;
+        fault.frame.xpsr |= 1 << 9;
+        let mut lines = Vec::new();
+        fault.report(|line| lines.push(line.to_string()));
+        assert_eq!(
+            lines[2],
+            "frame stacked with FPU registers, padded to 8 bytes"
+        );
+    }
+}
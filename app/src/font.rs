@@ -0,0 +1,169 @@
+//! A 5x5 bitmap font covering printable ASCII.
+//!
+//! Each glyph is five rows, top first, with the leftmost column held in
+//! bit 4 of each row.
+
+use crate::display::{Image, COLS, MAX_LEVEL, ROWS};
+
+pub type Glyph = [u8; ROWS];
+
+pub const FIRST_CHAR: char = ' ';
+pub const LAST_CHAR: char = '~';
+
+/// Shown for any character that the font has no glyph for.
+pub const REPLACEMENT_CHAR: char = '?';
+
+const GLYPHS: [Glyph; 95] = [
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000], // ' '
+    [0b00100, 0b00100, 0b00100, 0b00000, 0b00100], // '!'
+    [0b01010, 0b01010, 0b00000, 0b00000, 0b00000], // '"'
+    [0b01010, 0b11111, 0b01010, 0b11111, 0b01010], // '#'
+    [0b01111, 0b10100, 0b01110, 0b00101, 0b11110], // '$'
+    [0b11001, 0b10010, 0b00100, 0b01001, 0b10011], // '%'
+    [0b01100, 0b10010, 0b01100, 0b10010, 0b01101], // '&'
+    [0b00100, 0b00100, 0b00000, 0b00000, 0b00000], // '\''
+    [0b00010, 0b00100, 0b00100, 0b00100, 0b00010], // '('
+    [0b01000, 0b00100, 0b00100, 0b00100, 0b01000], // ')'
+    [0b00000, 0b01010, 0b00100, 0b01010, 0b00000], // '*'
+    [0b00000, 0b00100, 0b01110, 0b00100, 0b00000], // '+'
+    [0b00000, 0b00000, 0b00000, 0b00100, 0b01000], // ','
+    [0b00000, 0b00000, 0b01110, 0b00000, 0b00000], // '-'
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00100], // '.'
+    [0b00001, 0b00010, 0b00100, 0b01000, 0b10000], // '/'
+    [0b01100, 0b10010, 0b10010, 0b10010, 0b01100], // '0'
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b01110], // '1'
+    [0b11100, 0b00010, 0b01100, 0b10000, 0b11110], // '2'
+    [0b11110, 0b00010, 0b00100, 0b10010, 0b01100], // '3'
+    [0b00110, 0b01010, 0b10010, 0b11111, 0b00010], // '4'
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b11110], // '5'
+    [0b00010, 0b00100, 0b01110, 0b10001, 0b01110], // '6'
+    [0b11111, 0b00010, 0b00100, 0b01000, 0b10000], // '7'
+    [0b01110, 0b10001, 0b01110, 0b10001, 0b01110], // '8'
+    [0b01110, 0b10001, 0b01110, 0b00100, 0b01000], // '9'
+    [0b00000, 0b00100, 0b00000, 0b00100, 0b00000], // ':'
+    [0b00000, 0b00100, 0b00000, 0b00100, 0b01000], // ';'
+    [0b00010, 0b00100, 0b01000, 0b00100, 0b00010], // '<'
+    [0b00000, 0b01110, 0b00000, 0b01110, 0b00000], // '='
+    [0b01000, 0b00100, 0b00010, 0b00100, 0b01000], // '>'
+    [0b01110, 0b00001, 0b00110, 0b00000, 0b00100], // '?'
+    [0b01110, 0b10001, 0b10101, 0b10110, 0b01100], // '@'
+    [0b01100, 0b10010, 0b11110, 0b10010, 0b10010], // 'A'
+    [0b11100, 0b10010, 0b11100, 0b10010, 0b11100], // 'B'
+    [0b01110, 0b10000, 0b10000, 0b10000, 0b01110], // 'C'
+    [0b11100, 0b10010, 0b10010, 0b10010, 0b11100], // 'D'
+    [0b11110, 0b10000, 0b11100, 0b10000, 0b11110], // 'E'
+    [0b11110, 0b10000, 0b11100, 0b10000, 0b10000], // 'F'
+    [0b01110, 0b10000, 0b10011, 0b10001, 0b01110], // 'G'
+    [0b10010, 0b10010, 0b11110, 0b10010, 0b10010], // 'H'
+    [0b01110, 0b00100, 0b00100, 0b00100, 0b01110], // 'I'
+    [0b11111, 0b00010, 0b00010, 0b10010, 0b01100], // 'J'
+    [0b10010, 0b10100, 0b11000, 0b10100, 0b10010], // 'K'
+    [0b10000, 0b10000, 0b10000, 0b10000, 0b11110], // 'L'
+    [0b10001, 0b11011, 0b10101, 0b10001, 0b10001], // 'M'
+    [0b10001, 0b11001, 0b10101, 0b10011, 0b10001], // 'N'
+    [0b01100, 0b10010, 0b10010, 0b10010, 0b01100], // 'O'
+    [0b11100, 0b10010, 0b11100, 0b10000, 0b10000], // 'P'
+    [0b01100, 0b10010, 0b10010, 0b01100, 0b00010], // 'Q'
+    [0b11100, 0b10010, 0b11100, 0b10100, 0b10010], // 'R'
+    [0b01110, 0b10000, 0b01100, 0b00010, 0b11100], // 'S'
+    [0b11111, 0b00100, 0b00100, 0b00100, 0b00100], // 'T'
+    [0b10010, 0b10010, 0b10010, 0b10010, 0b01100], // 'U'
+    [0b10001, 0b10001, 0b10001, 0b01010, 0b00100], // 'V'
+    [0b10001, 0b10001, 0b10101, 0b11011, 0b10001], // 'W'
+    [0b10010, 0b10010, 0b01100, 0b10010, 0b10010], // 'X'
+    [0b10001, 0b01010, 0b00100, 0b00100, 0b00100], // 'Y'
+    [0b11110, 0b00100, 0b01000, 0b10000, 0b11110], // 'Z'
+    [0b01110, 0b01000, 0b01000, 0b01000, 0b01110], // '['
+    [0b10000, 0b01000, 0b00100, 0b00010, 0b00001], // '\\'
+    [0b01110, 0b00010, 0b00010, 0b00010, 0b01110], // ']'
+    [0b00100, 0b01010, 0b00000, 0b00000, 0b00000], // '^'
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b11111], // '_'
+    [0b01000, 0b00100, 0b00000, 0b00000, 0b00000], // '`'
+    [0b00000, 0b01110, 0b10010, 0b10010, 0b01111], // 'a'
+    [0b10000, 0b10000, 0b11100, 0b10010, 0b11100], // 'b'
+    [0b00000, 0b01110, 0b10000, 0b10000, 0b01110], // 'c'
+    [0b00010, 0b00010, 0b01110, 0b10010, 0b01110], // 'd'
+    [0b01100, 0b10010, 0b11100, 0b10000, 0b01110], // 'e'
+    [0b00110, 0b01000, 0b11100, 0b01000, 0b01000], // 'f'
+    [0b01110, 0b10010, 0b01110, 0b00010, 0b01100], // 'g'
+    [0b10000, 0b10000, 0b11100, 0b10010, 0b10010], // 'h'
+    [0b01000, 0b00000, 0b01000, 0b01000, 0b01000], // 'i'
+    [0b00010, 0b00000, 0b00010, 0b10010, 0b01100], // 'j'
+    [0b10000, 0b10100, 0b11000, 0b10100, 0b10010], // 'k'
+    [0b01000, 0b01000, 0b01000, 0b01000, 0b00110], // 'l'
+    [0b00000, 0b11010, 0b10101, 0b10001, 0b10001], // 'm'
+    [0b00000, 0b11100, 0b10010, 0b10010, 0b10010], // 'n'
+    [0b00000, 0b01100, 0b10010, 0b10010, 0b01100], // 'o'
+    [0b00000, 0b11100, 0b10010, 0b11100, 0b10000], // 'p'
+    [0b00000, 0b01110, 0b10010, 0b01110, 0b00010], // 'q'
+    [0b00000, 0b01110, 0b10000, 0b10000, 0b10000], // 'r'
+    [0b00000, 0b00110, 0b01000, 0b00100, 0b11000], // 's'
+    [0b01000, 0b11100, 0b01000, 0b01000, 0b00110], // 't'
+    [0b00000, 0b10010, 0b10010, 0b10010, 0b01110], // 'u'
+    [0b00000, 0b10001, 0b10001, 0b01010, 0b00100], // 'v'
+    [0b00000, 0b10001, 0b10101, 0b10101, 0b01010], // 'w'
+    [0b00000, 0b10010, 0b01100, 0b01100, 0b10010], // 'x'
+    [0b00000, 0b10010, 0b01110, 0b00010, 0b01100], // 'y'
+    [0b00000, 0b11110, 0b00100, 0b01000, 0b11110], // 'z'
+    [0b00110, 0b00100, 0b01100, 0b00100, 0b00110], // '{'
+    [0b00100, 0b00100, 0b00100, 0b00100, 0b00100], // '|'
+    [0b01100, 0b00100, 0b00110, 0b00100, 0b01100], // '}'
+    [0b00000, 0b00000, 0b01101, 0b10010, 0b00000], // '~'
+];
+
+/// The glyph for a character, falling back to `REPLACEMENT_CHAR`.
+pub fn glyph(c: char) -> &'static Glyph {
+    let c = if (FIRST_CHAR..=LAST_CHAR).contains(&c) {
+        c
+    } else {
+        REPLACEMENT_CHAR
+    };
+    &GLYPHS[c as usize - FIRST_CHAR as usize]
+}
+
+/// Whether the pixel at `row` and `col` of a glyph is lit.
+pub fn is_lit(glyph: &Glyph, row: usize, col: usize) -> bool {
+    glyph[row] & (1 << (COLS - 1 - col)) != 0
+}
+
+/// A character drawn at full level as a whole image.
+pub fn image(c: char) -> Image {
+    Image::from_bits(glyph(c), MAX_LEVEL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_space_is_blank() {
+        assert_eq!(image(' '), Image::blank());
+    }
+
+    #[test]
+    fn test_glyph_rendering() {
+        assert_eq!(
+            image('T'),
+            Image::new([
+                [9, 9, 9, 9, 9],
+                [0, 0, 9, 0, 0],
+                [0, 0, 9, 0, 0],
+                [0, 0, 9, 0, 0],
+                [0, 0, 9, 0, 0],
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unknown_chars_are_replaced() {
+        assert_eq!(glyph('\n'), glyph(REPLACEMENT_CHAR));
+        assert_eq!(glyph('é'), glyph(REPLACEMENT_CHAR));
+    }
+
+    #[test]
+    fn test_printable_glyphs_other_than_space_are_lit() {
+        for c in (FIRST_CHAR..=LAST_CHAR).skip(1) {
+            assert!(glyph(c).iter().any(|row| *row != 0), "{:?} is blank", c);
+        }
+    }
+}
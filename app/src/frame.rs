@@ -0,0 +1,526 @@
+//! Framing for binary payloads sent over a byte stream such as a UART.
+//!
+//! Each payload has a CRC-16 appended, and the lot is COBS encoded so that
+//! it contains no zero bytes. A zero byte then marks the end of each frame,
+//! which lets a receiver find the start of the next frame after noise, a
+//! dropped byte or a frame that is too long. Frames are also led by a zero,
+//! so that one cut off part way cannot spoil the one after it.
+
+pub const MAX_PAYLOAD_LEN: usize = 250;
+
+const CRC_LEN: usize = 2;
+
+/// The longest a frame with a `MAX_PAYLOAD_LEN` payload can encode to,
+/// including its delimiters.
+pub const MAX_FRAME_LEN: usize = cobs_max_len(MAX_PAYLOAD_LEN + CRC_LEN) + 2;
+
+const DELIMITER: u8 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// There is not enough room for the output.
+    BufferTooSmall,
+    /// The payload is longer than `MAX_PAYLOAD_LEN`.
+    TooLong,
+    /// The frame is not valid COBS.
+    Malformed,
+    /// The frame is too short to hold a CRC.
+    TooShort,
+    /// The frame was damaged on the way.
+    BadCrc,
+}
+
+/// CRC-16/CCITT-FALSE, as used by XMODEM's successors and many others.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xffff_u16;
+    for byte in data {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// The most that `len` bytes can COBS encode to.
+pub const fn cobs_max_len(len: usize) -> usize {
+    len + len / 254 + 1
+}
+
+/// COBS encode `src` into `dst`, returning the encoded length. The output
+/// contains no zero bytes and no delimiter is added.
+pub fn cobs_encode(src: &[u8], dst: &mut [u8]) -> Result<usize, Error> {
+    let mut code_at = 0;
+    let mut len = 1;
+    let mut code = 1_u8;
+    for (index, byte) in src.iter().enumerate() {
+        if *byte != 0 {
+            *dst.get_mut(len).ok_or(Error::BufferTooSmall)? = *byte;
+            len += 1;
+            code += 1;
+        }
+        // A full block needs no zero after it, so only ends if more follows
+        if *byte == 0 || (code == 0xff && index + 1 < src.len()) {
+            *dst.get_mut(code_at).ok_or(Error::BufferTooSmall)? = code;
+            code_at = len;
+            len += 1;
+            code = 1;
+        }
+    }
+    *dst.get_mut(code_at).ok_or(Error::BufferTooSmall)? = code;
+    Ok(len)
+}
+
+/// COBS decode `src` into `dst`, returning the decoded length. `src` must
+/// not include the delimiter.
+pub fn cobs_decode(src: &[u8], dst: &mut [u8]) -> Result<usize, Error> {
+    let mut index = 0;
+    let mut len = 0;
+    while index < src.len() {
+        let code = usize::from(src[index]);
+        let end = index + code;
+        if code == 0 || end > src.len() {
+            return Err(Error::Malformed);
+        }
+        for byte in &src[index + 1..end] {
+            if *byte == 0 {
+                return Err(Error::Malformed);
+            }
+            *dst.get_mut(len).ok_or(Error::BufferTooSmall)? = *byte;
+            len += 1;
+        }
+        index = end;
+        // Each block but the last stands for a zero, unless it is full
+        if code != 0xff && index < src.len() {
+            *dst.get_mut(len).ok_or(Error::BufferTooSmall)? = 0;
+            len += 1;
+        }
+    }
+    Ok(len)
+}
+
+/// Encode a payload as a complete frame, delimiters included, returning its
+/// length. `dst` needs `MAX_FRAME_LEN` bytes to be sure of fitting.
+pub fn encode(payload: &[u8], dst: &mut [u8]) -> Result<usize, Error> {
+    if payload.len() > MAX_PAYLOAD_LEN {
+        return Err(Error::TooLong);
+    }
+    let mut raw = [0; MAX_PAYLOAD_LEN + CRC_LEN];
+    raw[..payload.len()].copy_from_slice(payload);
+    let crc = crc16(payload);
+    raw[payload.len()..payload.len() + CRC_LEN].copy_from_slice(&crc.to_be_bytes());
+    *dst.first_mut().ok_or(Error::BufferTooSmall)? = DELIMITER;
+    let len = 1 + cobs_encode(&raw[..payload.len() + CRC_LEN], &mut dst[1..])?;
+    *dst.get_mut(len).ok_or(Error::BufferTooSmall)? = DELIMITER;
+    Ok(len + 1)
+}
+
+/// Reassembles frames from a byte stream, one byte at a time.
+pub struct Decoder {
+    buffer: [u8; MAX_FRAME_LEN],
+    len: usize,
+    overflowed: bool,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self {
+            buffer: [0; MAX_FRAME_LEN],
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Take the next byte of the stream, returning the payload of a frame
+    /// when its delimiter arrives, or why the frame was no good. Either
+    /// way, decoding starts afresh with the next byte.
+    pub fn feed(&mut self, byte: u8) -> Option<Result<&[u8], Error>> {
+        if byte != DELIMITER {
+            if self.len < self.buffer.len() {
+                self.buffer[self.len] = byte;
+                self.len += 1;
+            } else {
+                self.overflowed = true;
+            }
+            return None;
+        }
+
+        let len = core::mem::replace(&mut self.len, 0);
+        if core::mem::replace(&mut self.overflowed, false) {
+            return Some(Err(Error::TooLong));
+        }
+        if len == 0 {
+            // Back to back delimiters, used to flush out any noise
+            return None;
+        }
+        Some(self.decode(len))
+    }
+
+    fn decode(&mut self, len: usize) -> Result<&[u8], Error> {
+        let mut decoded = [0; MAX_PAYLOAD_LEN + CRC_LEN];
+        let len = cobs_decode(&self.buffer[..len], &mut decoded).map_err(|error| match error {
+            Error::BufferTooSmall => Error::TooLong,
+            error => error,
+        })?;
+        self.buffer[..len].copy_from_slice(&decoded[..len]);
+        if len < CRC_LEN {
+            return Err(Error::TooShort);
+        }
+        let (payload, crc) = self.buffer[..len].split_at(len - CRC_LEN);
+        if payload.len() > MAX_PAYLOAD_LEN {
+            return Err(Error::TooLong);
+        }
+        if crc16(payload).to_be_bytes() != crc {
+            return Err(Error::BadCrc);
+        }
+        Ok(payload)
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What arrived on a line carrying both text and frames.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Received<'a> {
+    Text(u8),
+    Frame(Result<&'a [u8], Error>),
+}
+
+/// Separates frames from text on a line that carries both. Text never
+/// contains a zero byte, so everything from the zero leading a frame up to
+/// the end of that frame is taken as the frame.
+///
+/// A frame cut short, such as by the host tool being killed, or a stray
+/// zero from noise, would otherwise swallow all the text after it until the
+/// next zero, which can't be typed. So once more has arrived than any frame
+/// could hold, the frame is given up on as too long, and what follows is
+/// text again.
+pub struct Demux {
+    decoder: Decoder,
+    in_frame: bool,
+}
+
+impl Demux {
+    pub const fn new() -> Self {
+        Self {
+            decoder: Decoder::new(),
+            in_frame: false,
+        }
+    }
+
+    pub fn feed(&mut self, byte: u8) -> Option<Received<'_>> {
+        if byte != DELIMITER && !self.in_frame {
+            return Some(Received::Text(byte));
+        }
+        if byte != DELIMITER && self.decoder.len == self.decoder.buffer.len() {
+            self.decoder = Decoder::new();
+            self.in_frame = false;
+            return Some(Received::Frame(Err(Error::TooLong)));
+        }
+        match self.decoder.feed(byte) {
+            None => {
+                self.in_frame = true;
+                None
+            }
+            Some(result) => {
+                self.in_frame = false;
+                Some(Received::Frame(result))
+            }
+        }
+    }
+}
+
+impl Default for Demux {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small, seeded generator so the fuzz tests are repeatable.
+    struct XorShift(u32);
+
+    impl XorShift {
+        fn next(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            self.next() as usize % n
+        }
+
+        /// Bytes with plenty of zeros and long runs without.
+        fn payload(&mut self, len: usize) -> Vec<u8> {
+            let zeros = self.below(4);
+            (0..len)
+                .map(|_| match zeros {
+                    0 => 0,
+                    1 => (self.next() as u8).max(1),
+                    _ => self.next() as u8,
+                })
+                .collect()
+        }
+    }
+
+    fn cobs(src: &[u8]) -> Vec<u8> {
+        let mut dst = vec![0; cobs_max_len(src.len())];
+        let len = cobs_encode(src, &mut dst).unwrap();
+        dst.truncate(len);
+        dst
+    }
+
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut dst = vec![0; MAX_FRAME_LEN];
+        let len = encode(payload, &mut dst).unwrap();
+        dst.truncate(len);
+        dst
+    }
+
+    fn decode_all(decoder: &mut Decoder, bytes: &[u8]) -> Vec<Result<Vec<u8>, Error>> {
+        bytes
+            .iter()
+            .filter_map(|byte| decoder.feed(*byte).map(|result| result.map(<[u8]>::to_vec)))
+            .collect()
+    }
+
+    #[test]
+    fn test_crc16_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+        assert_eq!(crc16(b""), 0xffff);
+    }
+
+    #[test]
+    fn test_cobs_known_encodings() {
+        assert_eq!(cobs(&[]), vec![0x01]);
+        assert_eq!(cobs(&[0x00]), vec![0x01, 0x01]);
+        assert_eq!(cobs(&[0x00, 0x00]), vec![0x01, 0x01, 0x01]);
+        assert_eq!(
+            cobs(&[0x11, 0x22, 0x00, 0x33]),
+            vec![0x03, 0x11, 0x22, 0x02, 0x33]
+        );
+        assert_eq!(cobs(&[0x11, 0x00]), vec![0x02, 0x11, 0x01]);
+
+        let run: Vec<u8> = (1..=254).collect();
+        let mut expected = vec![0xff];
+        expected.extend(&run);
+        assert_eq!(cobs(&run), expected);
+
+        let run: Vec<u8> = (1..=255).collect();
+        let mut expected = vec![0xff];
+        expected.extend(1..=254);
+        expected.extend([0x02, 0xff]);
+        assert_eq!(cobs(&run), expected);
+    }
+
+    #[test]
+    fn test_cobs_rejects_malformed_input() {
+        let mut dst = [0; 16];
+        assert_eq!(cobs_decode(&[0x03, 0x11], &mut dst), Err(Error::Malformed));
+        assert_eq!(cobs_decode(&[0x02, 0x00], &mut dst), Err(Error::Malformed));
+        assert_eq!(cobs_decode(&[0x00], &mut dst), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn test_cobs_output_must_fit() {
+        assert_eq!(
+            cobs_encode(&[1, 2, 3], &mut [0; 3]),
+            Err(Error::BufferTooSmall)
+        );
+        assert_eq!(
+            cobs_decode(&[0x04, 1, 2, 3], &mut [0; 2]),
+            Err(Error::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_fuzz_cobs_round_trip() {
+        let mut rng = XorShift(0x1234_5678);
+        for _ in 0..2_000 {
+            let len = rng.below(600);
+            let src = rng.payload(len);
+            let encoded = cobs(&src);
+            assert!(!encoded.contains(&0));
+            assert!(encoded.len() <= cobs_max_len(src.len()));
+            let mut decoded = vec![0; encoded.len()];
+            let len = cobs_decode(&encoded, &mut decoded).unwrap();
+            assert_eq!(&decoded[..len], &src[..]);
+        }
+    }
+
+    #[test]
+    fn test_frames_round_trip() {
+        let mut decoder = Decoder::new();
+        for payload in [&b""[..], b"\x00", b"hello", &[0; MAX_PAYLOAD_LEN]].iter() {
+            assert_eq!(
+                decode_all(&mut decoder, &frame(payload)),
+                vec![Ok(payload.to_vec())]
+            );
+        }
+    }
+
+    #[test]
+    fn test_payload_length_is_enforced() {
+        let mut dst = [0; MAX_FRAME_LEN + 16];
+        assert_eq!(
+            encode(&[1; MAX_PAYLOAD_LEN + 1], &mut dst),
+            Err(Error::TooLong)
+        );
+        assert_eq!(encode(b"hello", &mut [0; 4]), Err(Error::BufferTooSmall));
+
+        // A frame too long for the decoder, from a sender without the limit
+        let mut raw = vec![7; MAX_PAYLOAD_LEN + 1];
+        raw.extend(crc16(&raw.clone()).to_be_bytes());
+        let mut too_long = cobs(&raw);
+        too_long.push(0);
+        too_long.extend(frame(b"next"));
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            decode_all(&mut decoder, &too_long),
+            vec![Err(Error::TooLong), Ok(b"next".to_vec())]
+        );
+
+        let mut endless = vec![1; MAX_FRAME_LEN * 2];
+        endless.push(0);
+        endless.extend(frame(b"next"));
+        assert_eq!(
+            decode_all(&mut decoder, &endless),
+            vec![Err(Error::TooLong), Ok(b"next".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_decoder_recovers_from_damage() {
+        let mut decoder = Decoder::new();
+
+        let mut corrupt = frame(b"hello");
+        corrupt[3] ^= 0x10;
+        corrupt.extend(frame(b"world"));
+        assert_eq!(
+            decode_all(&mut decoder, &corrupt),
+            vec![Err(Error::BadCrc), Ok(b"world".to_vec())]
+        );
+
+        // Joining in part way through a frame
+        let truncated = frame(b"hello");
+        let mut stream = truncated[3..].to_vec();
+        stream.extend(frame(b"world"));
+        let results = decode_all(&mut decoder, &stream);
+        assert!(results[0].is_err());
+        assert_eq!(results[1], Ok(b"world".to_vec()));
+
+        assert_eq!(
+            decode_all(&mut decoder, &[0, 0, 0x01, 0x01, 0]),
+            vec![Err(Error::TooShort)]
+        );
+    }
+
+    #[test]
+    fn test_demux_separates_text_and_frames() {
+        let mut stream = b"ab".to_vec();
+        stream.extend(frame(b"one"));
+        stream.extend(b"c");
+        let mut bad = frame(b"two");
+        bad[2] ^= 0x01;
+        stream.extend(bad);
+        stream.extend(frame(b"three"));
+
+        let mut demux = Demux::new();
+        let mut text = Vec::new();
+        let mut frames = Vec::new();
+        for byte in stream {
+            match demux.feed(byte) {
+                Some(Received::Text(byte)) => text.push(byte),
+                Some(Received::Frame(result)) => frames.push(result.map(<[u8]>::to_vec)),
+                None => (),
+            }
+        }
+        assert_eq!(text, b"abc".to_vec());
+        assert_eq!(
+            frames,
+            vec![
+                Ok(b"one".to_vec()),
+                Err(Error::BadCrc),
+                Ok(b"three".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_demux_gives_up_on_a_frame_cut_short() {
+        let mut stream = frame(b"one");
+        stream.truncate(4);
+        // Enough typing to be sure the frame has no end coming
+        let typed: Vec<u8> = b"help\r\n"
+            .iter()
+            .copied()
+            .cycle()
+            .take(MAX_FRAME_LEN)
+            .collect();
+        stream.extend(&typed);
+        stream.extend(b"led clear\r\n");
+        stream.extend(frame(b"two"));
+
+        let mut demux = Demux::new();
+        let mut text = Vec::new();
+        let mut frames = Vec::new();
+        for byte in stream {
+            match demux.feed(byte) {
+                Some(Received::Text(byte)) => text.push(byte),
+                Some(Received::Frame(result)) => frames.push(result.map(<[u8]>::to_vec)),
+                None => (),
+            }
+        }
+        assert!(text.ends_with(b"led clear\r\n"));
+        assert_eq!(frames, vec![Err(Error::TooLong), Ok(b"two".to_vec())]);
+    }
+
+    #[test]
+    fn test_fuzz_streams_of_damaged_frames() {
+        let mut rng = XorShift(0x9e37_79b9);
+        let mut decoder = Decoder::new();
+        for _ in 0..500 {
+            let len = rng.below(MAX_PAYLOAD_LEN + 1);
+            let payload = rng.payload(len);
+            let mut bytes = frame(&payload);
+            let damaged = rng.below(3) == 0;
+            if damaged {
+                // Somewhere between the delimiters
+                let at = 1 + rng.below(bytes.len() - 2);
+                match rng.below(3) {
+                    0 => bytes[at] ^= (rng.next() as u8).max(1),
+                    1 => {
+                        bytes.remove(at);
+                    }
+                    _ => bytes.truncate(at),
+                }
+            }
+            let mut stream = bytes;
+            stream.extend(frame(b"sync"));
+
+            let results = decode_all(&mut decoder, &stream);
+            let last = results.last().unwrap();
+            assert_eq!(last, &Ok(b"sync".to_vec()));
+            for result in &results[..results.len() - 1] {
+                if damaged {
+                    assert_ne!(result, &Ok(payload.clone()));
+                } else {
+                    assert_eq!(result, &Ok(payload.clone()));
+                }
+            }
+        }
+    }
+}
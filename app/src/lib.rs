@@ -1,5 +1,25 @@
 #![cfg_attr(not(test), no_std)]
 
+pub mod animation;
+pub mod build_info;
+pub mod button;
+pub mod crash;
+pub mod display;
+pub mod fault;
+pub mod font;
+pub mod frame;
+pub mod log;
+pub mod lsm303agr;
+pub mod matrix;
+pub mod message;
+pub mod queue;
+pub mod reset;
+pub mod scroll;
+pub mod shell;
+pub mod transform;
+pub mod transition;
+pub mod watchdog;
+
 /// A demonstration of writing some application library code that is able
 /// to run in any no_std environment, and can be tested nicely.
 /// ```
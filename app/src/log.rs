@@ -0,0 +1,178 @@
+//! Levelled log records, and a maximum level to filter them by that can be
+//! changed while running.
+//!
+//! Where records end up is for the firmware to decide. Each is shown on a
+//! line of its own, with the time since startup, its level and a tag naming
+//! the module it came from:
+//!
+//! ```text
+//!     12.345 INFO  console: Ready
+//! ```
+
+use core::fmt;
+use core::str::FromStr;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// How important a record is, from most to least.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+const LEVELS: [Level; 5] = [
+    Level::Error,
+    Level::Warn,
+    Level::Info,
+    Level::Debug,
+    Level::Trace,
+];
+
+impl Level {
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseLevelError;
+
+impl FromStr for Level {
+    type Err = ParseLevelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LEVELS
+            .iter()
+            .find(|level| level.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or(ParseLevelError)
+    }
+}
+
+/// The least important level to let through, if any.
+pub struct MaxLevel(AtomicU8);
+
+impl MaxLevel {
+    pub const fn new(level: Option<Level>) -> Self {
+        Self(AtomicU8::new(match level {
+            Some(level) => level as u8,
+            None => 0,
+        }))
+    }
+
+    pub fn get(&self) -> Option<Level> {
+        let value = self.0.load(Ordering::Relaxed);
+        LEVELS.iter().find(|level| **level as u8 == value).copied()
+    }
+
+    pub fn set(&self, level: Option<Level>) {
+        self.0
+            .store(level.map_or(0, |level| level as u8), Ordering::Relaxed);
+    }
+
+    pub fn enabled(&self, level: Level) -> bool {
+        level as u8 <= self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The last part of a module path, such as `console` for
+/// `microrust_start::console`.
+pub fn tag(module_path: &str) -> &str {
+    module_path.rsplit("::").next().unwrap_or(module_path)
+}
+
+/// A message to log, shown as a line without its line ending.
+pub struct Record<'a> {
+    pub at_ms: u32,
+    pub level: Level,
+    pub tag: &'a str,
+    pub args: fmt::Arguments<'a>,
+}
+
+impl<'a> fmt::Display for Record<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN ",
+            Level::Info => "INFO ",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        };
+        write!(
+            f,
+            "{:>6}.{:03} {} {}: {}",
+            self.at_ms / 1_000,
+            self.at_ms % 1_000,
+            level,
+            self.tag,
+            self.args
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_layout() {
+        let record = Record {
+            at_ms: 12_345,
+            level: Level::Info,
+            tag: "console",
+            args: format_args!("Ready after {} tries", 2),
+        };
+        assert_eq!(
+            record.to_string(),
+            "    12.345 INFO  console: Ready after 2 tries"
+        );
+        let record = Record {
+            at_ms: 7,
+            level: Level::Error,
+            tag: "main",
+            args: format_args!("Oops"),
+        };
+        assert_eq!(record.to_string(), "     0.007 ERROR main: Oops");
+    }
+
+    #[test]
+    fn test_tag_is_the_last_part_of_the_module_path() {
+        assert_eq!(tag("microrust_start::console"), "console");
+        assert_eq!(tag("microrust_start"), "microrust_start");
+        assert_eq!(tag(module_path!()), "tests");
+    }
+
+    #[test]
+    fn test_max_level_filters() {
+        let max_level = MaxLevel::new(Some(Level::Info));
+        assert!(max_level.enabled(Level::Error));
+        assert!(max_level.enabled(Level::Info));
+        assert!(!max_level.enabled(Level::Debug));
+
+        max_level.set(Some(Level::Trace));
+        assert!(max_level.enabled(Level::Trace));
+        assert_eq!(max_level.get(), Some(Level::Trace));
+
+        max_level.set(None);
+        assert!(!max_level.enabled(Level::Error));
+        assert_eq!(max_level.get(), None);
+    }
+
+    #[test]
+    fn test_parse_level() {
+        for level in LEVELS.iter() {
+            assert_eq!(level.name().parse(), Ok(*level));
+        }
+        assert_eq!("WARN".parse(), Ok(Level::Warn));
+        assert_eq!("loud".parse::<Level>(), Err(ParseLevelError));
+    }
+}
@@ -0,0 +1,664 @@
+//! A driver for the LSM303AGR, the accelerometer and magnetometer on the
+//! micro:bit's internal I2C bus, over the `embedded-hal` blocking I2C
+//! traits.
+//!
+//! The two sensors answer at addresses of their own. The accelerometer
+//! runs in its 12 bit high resolution mode, and can raise data-ready and
+//! motion interrupts on its INT1 line, driven active low so as to share the
+//! board's interrupt line. The magnetometer runs continuously, with its
+//! temperature compensation on. Both only update their outputs once all
+//! three axes have been read, so that a reading is never torn across two
+//! samples.
+
+use core::fmt;
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+pub const ACCEL_ADDRESS: u8 = 0x19;
+pub const MAG_ADDRESS: u8 = 0x1e;
+
+const ACCEL_ID: u8 = 0x33;
+const MAG_ID: u8 = 0x40;
+
+const WHO_AM_I_A: u8 = 0x0f;
+const CTRL_REG1_A: u8 = 0x20;
+const CTRL_REG3_A: u8 = 0x22;
+const CTRL_REG4_A: u8 = 0x23;
+const CTRL_REG5_A: u8 = 0x24;
+const CTRL_REG6_A: u8 = 0x25;
+const STATUS_REG_A: u8 = 0x27;
+const OUT_X_L_A: u8 = 0x28;
+const INT1_CFG_A: u8 = 0x30;
+const INT1_SRC_A: u8 = 0x31;
+const INT1_THS_A: u8 = 0x32;
+const INT1_DURATION_A: u8 = 0x33;
+const WHO_AM_I_M: u8 = 0x4f;
+const CFG_REG_A_M: u8 = 0x60;
+const CFG_REG_C_M: u8 = 0x62;
+const STATUS_REG_M: u8 = 0x67;
+const OUTX_L_REG_M: u8 = 0x68;
+
+/// Set on an accelerometer register address to read on through those
+/// after it. The magnetometer always does.
+const AUTO_INCREMENT: u8 = 0x80;
+
+// CTRL_REG1_A
+const XYZ_ENABLE: u8 = 0b111;
+// CTRL_REG3_A
+const I1_AOI1: u8 = 1 << 6;
+const I1_DRDY1: u8 = 1 << 4;
+// CTRL_REG4_A
+const BDU_A: u8 = 1 << 7;
+const HR: u8 = 1 << 3;
+// CTRL_REG5_A
+const LIR_INT1: u8 = 1 << 3;
+// CTRL_REG6_A
+const H_LACTIVE: u8 = 1 << 1;
+// STATUS_REG_A and STATUS_REG_M
+const ZYXDA: u8 = 1 << 3;
+// INT1_CFG_A, an interrupt on any axis going above the threshold
+const XYZ_HIGH: u8 = 0b10_1010;
+// INT1_SRC_A
+const IA: u8 = 1 << 6;
+// CFG_REG_A_M, which is in continuous mode with the mode bits clear
+const COMP_TEMP_EN: u8 = 1 << 7;
+// CFG_REG_C_M
+const BDU_M: u8 = 1 << 4;
+
+/// The largest value of INT1_THS_A and INT1_DURATION_A.
+const MAX_INT1_STEPS: u32 = 127;
+const MAG_NT_PER_DIGIT: i32 = 150;
+
+/// How often the accelerometer samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccelRate {
+    PowerDown = 0,
+    Hz1 = 1,
+    Hz10 = 2,
+    Hz25 = 3,
+    Hz50 = 4,
+    Hz100 = 5,
+    Hz200 = 6,
+    Hz400 = 7,
+    Hz1344 = 9,
+}
+
+impl AccelRate {
+    pub fn hz(self) -> u32 {
+        match self {
+            AccelRate::PowerDown => 0,
+            AccelRate::Hz1 => 1,
+            AccelRate::Hz10 => 10,
+            AccelRate::Hz25 => 25,
+            AccelRate::Hz50 => 50,
+            AccelRate::Hz100 => 100,
+            AccelRate::Hz200 => 200,
+            AccelRate::Hz400 => 400,
+            AccelRate::Hz1344 => 1344,
+        }
+    }
+
+    /// INT1_DURATION_A counts in samples, so the duration it holds for a
+    /// time depends on the rate. Powered down, nothing lasts.
+    fn duration_steps(self, duration_ms: u32) -> u8 {
+        (duration_ms.saturating_mul(self.hz()) / 1000).min(MAX_INT1_STEPS) as u8
+    }
+}
+
+/// The largest acceleration the accelerometer can measure, either way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccelScale {
+    G2 = 0,
+    G4 = 1,
+    G8 = 2,
+    G16 = 3,
+}
+
+impl AccelScale {
+    /// The sensitivity in high resolution mode, from the datasheet.
+    fn micro_g_per_digit(self) -> i32 {
+        match self {
+            AccelScale::G2 => 980,
+            AccelScale::G4 => 1950,
+            AccelScale::G8 => 3900,
+            AccelScale::G16 => 11720,
+        }
+    }
+
+    fn threshold_steps(self, threshold_mg: u32) -> u8 {
+        let mg_per_step = match self {
+            AccelScale::G2 => 16,
+            AccelScale::G4 => 32,
+            AccelScale::G8 => 62,
+            AccelScale::G16 => 186,
+        };
+        (threshold_mg / mg_per_step).min(MAX_INT1_STEPS) as u8
+    }
+}
+
+/// How often the magnetometer samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MagRate {
+    Hz10 = 0,
+    Hz20 = 1,
+    Hz50 = 2,
+    Hz100 = 3,
+}
+
+/// Movement of the board, seen as the acceleration on any axis going above
+/// a threshold for at least a duration. Gravity counts, so the threshold
+/// should be well over 1000 mg for a board lying flat not to set it off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Motion {
+    /// Rounded down to a step of the scale, and limited to 127 steps.
+    pub threshold_mg: u32,
+    /// Rounded down to a sample at the rate, and limited to 127 samples.
+    pub duration_ms: u32,
+}
+
+/// What pulls the interrupt line low.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Interrupts {
+    /// Until the acceleration is read.
+    pub data_ready: bool,
+    /// Until `motion_detected` is called.
+    pub motion: Option<Motion>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Vector {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Vector {
+    /// Three little endian words, such as the sensors give, scaled by
+    /// `scale` after being shifted right by `shift`.
+    fn from_le_bytes(bytes: [u8; 6], shift: u32, scale: impl Fn(i32) -> i32) -> Self {
+        let axis = |at: usize| {
+            scale(i32::from(
+                i16::from_le_bytes([bytes[at], bytes[at + 1]]) >> shift,
+            ))
+        };
+        Self {
+            x: axis(0),
+            y: axis(2),
+            z: axis(4),
+        }
+    }
+}
+
+impl fmt::Display for Vector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    Bus(E),
+    /// Something other than the sensor expected answered at `address`.
+    UnknownDevice {
+        address: u8,
+        id: u8,
+    },
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Bus(error) => write!(f, "I2C bus error: {:?}", error),
+            Error::UnknownDevice { address, id } => {
+                write!(f, "unknown device {:#04x} at {:#04x}", id, address)
+            }
+        }
+    }
+}
+
+pub struct Lsm303agr<I2C> {
+    i2c: I2C,
+    accel_rate: AccelRate,
+    accel_scale: AccelScale,
+    motion: Option<Motion>,
+}
+
+impl<I2C, E> Lsm303agr<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Nothing is sent until `init`.
+    pub fn new(i2c: I2C) -> Self {
+        Self {
+            i2c,
+            accel_rate: AccelRate::PowerDown,
+            accel_scale: AccelScale::G2,
+            motion: None,
+        }
+    }
+
+    /// Check that both sensors are there, then leave the accelerometer
+    /// powered down at ±2 g, and the magnetometer sampling at 10 Hz.
+    pub fn init(&mut self) -> Result<(), Error<E>> {
+        self.check_id(ACCEL_ADDRESS, WHO_AM_I_A, ACCEL_ID)?;
+        self.check_id(MAG_ADDRESS, WHO_AM_I_M, MAG_ID)?;
+        self.accel_rate = AccelRate::PowerDown;
+        self.accel_scale = AccelScale::G2;
+        self.motion = None;
+        self.write_accel_rate()?;
+        self.write_accel_scale()?;
+        self.write(ACCEL_ADDRESS, CTRL_REG6_A, H_LACTIVE)?;
+        self.set_interrupts(Interrupts::default())?;
+        self.write(MAG_ADDRESS, CFG_REG_C_M, BDU_M)?;
+        self.set_mag_rate(MagRate::Hz10)
+    }
+
+    pub fn set_accel_rate(&mut self, rate: AccelRate) -> Result<(), Error<E>> {
+        self.accel_rate = rate;
+        self.write_accel_rate()?;
+        self.write_motion_limits()
+    }
+
+    pub fn set_accel_scale(&mut self, scale: AccelScale) -> Result<(), Error<E>> {
+        self.accel_scale = scale;
+        self.write_accel_scale()?;
+        self.write_motion_limits()
+    }
+
+    pub fn set_mag_rate(&mut self, rate: MagRate) -> Result<(), Error<E>> {
+        self.write(MAG_ADDRESS, CFG_REG_A_M, COMP_TEMP_EN | (rate as u8) << 2)
+    }
+
+    /// Set what pulls the interrupt line low. The motion threshold and
+    /// duration follow later changes to the accelerometer's scale and rate.
+    pub fn set_interrupts(&mut self, interrupts: Interrupts) -> Result<(), Error<E>> {
+        self.motion = interrupts.motion;
+        self.write_motion_limits()?;
+        let (cfg, latched, route) = match interrupts.motion {
+            Some(_) => (XYZ_HIGH, LIR_INT1, I1_AOI1),
+            None => (0, 0, 0),
+        };
+        self.write(ACCEL_ADDRESS, INT1_CFG_A, cfg)?;
+        self.write(ACCEL_ADDRESS, CTRL_REG5_A, latched)?;
+        let data_ready = if interrupts.data_ready { I1_DRDY1 } else { 0 };
+        self.write(ACCEL_ADDRESS, CTRL_REG3_A, route | data_ready)
+    }
+
+    /// Whether the accelerometer has a sample that hasn't been read yet.
+    pub fn accel_ready(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read(ACCEL_ADDRESS, STATUS_REG_A)? & ZYXDA != 0)
+    }
+
+    /// Whether the magnetometer has a sample that hasn't been read yet.
+    pub fn mag_ready(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read(MAG_ADDRESS, STATUS_REG_M)? & ZYXDA != 0)
+    }
+
+    /// The latest acceleration, in milli-g.
+    pub fn acceleration(&mut self) -> Result<Vector, Error<E>> {
+        let mut bytes = [0; 6];
+        self.read_into(ACCEL_ADDRESS, OUT_X_L_A | AUTO_INCREMENT, &mut bytes)?;
+        let micro_g_per_digit = self.accel_scale.micro_g_per_digit();
+        // The 12 bits of high resolution mode are left justified
+        Ok(Vector::from_le_bytes(bytes, 4, |digits| {
+            digits * micro_g_per_digit / 1000
+        }))
+    }
+
+    /// The latest magnetic field, in nanotesla.
+    pub fn magnetic_field(&mut self) -> Result<Vector, Error<E>> {
+        let mut bytes = [0; 6];
+        self.read_into(MAG_ADDRESS, OUTX_L_REG_M, &mut bytes)?;
+        Ok(Vector::from_le_bytes(bytes, 0, |digits| {
+            digits * MAG_NT_PER_DIGIT
+        }))
+    }
+
+    /// Whether motion has been seen since last asked, which lets the
+    /// interrupt line go again.
+    pub fn motion_detected(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read(ACCEL_ADDRESS, INT1_SRC_A)? & IA != 0)
+    }
+
+    /// Give back the bus.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    fn check_id(&mut self, address: u8, register: u8, expected: u8) -> Result<(), Error<E>> {
+        match self.read(address, register)? {
+            id if id == expected => Ok(()),
+            id => Err(Error::UnknownDevice { address, id }),
+        }
+    }
+
+    fn write_accel_rate(&mut self) -> Result<(), Error<E>> {
+        let bits = (self.accel_rate as u8) << 4 | XYZ_ENABLE;
+        self.write(ACCEL_ADDRESS, CTRL_REG1_A, bits)
+    }
+
+    fn write_accel_scale(&mut self) -> Result<(), Error<E>> {
+        let bits = BDU_A | (self.accel_scale as u8) << 4 | HR;
+        self.write(ACCEL_ADDRESS, CTRL_REG4_A, bits)
+    }
+
+    fn write_motion_limits(&mut self) -> Result<(), Error<E>> {
+        let motion = match self.motion {
+            Some(motion) => motion,
+            None => return Ok(()),
+        };
+        let threshold = self.accel_scale.threshold_steps(motion.threshold_mg);
+        let duration = self.accel_rate.duration_steps(motion.duration_ms);
+        self.write(ACCEL_ADDRESS, INT1_THS_A, threshold)?;
+        self.write(ACCEL_ADDRESS, INT1_DURATION_A, duration)
+    }
+
+    fn write(&mut self, address: u8, register: u8, value: u8) -> Result<(), Error<E>> {
+        self.i2c
+            .write(address, &[register, value])
+            .map_err(Error::Bus)
+    }
+
+    fn read(&mut self, address: u8, register: u8) -> Result<u8, Error<E>> {
+        let mut value = [0];
+        self.read_into(address, register, &mut value)?;
+        Ok(value[0])
+    }
+
+    fn read_into(&mut self, address: u8, register: u8, buffer: &mut [u8]) -> Result<(), Error<E>> {
+        self.i2c
+            .write_read(address, &[register], buffer)
+            .map_err(Error::Bus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Nack;
+
+    /// The registers of both sensors, as they read and were last written.
+    struct FakeBus {
+        accel: [u8; 128],
+        mag: [u8; 128],
+        writes: Vec<(u8, u8, u8)>,
+    }
+
+    impl FakeBus {
+        fn new() -> Self {
+            let mut bus = Self {
+                accel: [0; 128],
+                mag: [0; 128],
+                writes: Vec::new(),
+            };
+            bus.accel[WHO_AM_I_A as usize] = ACCEL_ID;
+            bus.mag[WHO_AM_I_M as usize] = MAG_ID;
+            bus
+        }
+
+        fn registers(&mut self, address: u8) -> Result<&mut [u8; 128], Nack> {
+            match address {
+                ACCEL_ADDRESS => Ok(&mut self.accel),
+                MAG_ADDRESS => Ok(&mut self.mag),
+                _ => Err(Nack),
+            }
+        }
+
+        fn written(&self, address: u8, register: u8) -> Option<u8> {
+            self.writes
+                .iter()
+                .rev()
+                .find(|(a, r, _)| (*a, *r) == (address, register))
+                .map(|(_, _, value)| *value)
+        }
+    }
+
+    impl Write for FakeBus {
+        type Error = Nack;
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Nack> {
+            let (register, values) = bytes.split_first().ok_or(Nack)?;
+            assert_eq!(values.len(), 1, "one register at a time");
+            self.registers(address)?[*register as usize] = values[0];
+            self.writes.push((address, *register, values[0]));
+            Ok(())
+        }
+    }
+
+    impl WriteRead for FakeBus {
+        type Error = Nack;
+
+        fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Nack> {
+            let register = bytes[0];
+            let increments = address == MAG_ADDRESS || register & AUTO_INCREMENT != 0;
+            let start = (register & !AUTO_INCREMENT) as usize;
+            let registers = self.registers(address)?;
+            for (i, byte) in buffer.iter_mut().enumerate() {
+                *byte = registers[if increments { start + i } else { start }];
+            }
+            // Reading the source lets go of a latched interrupt
+            if address == ACCEL_ADDRESS && start == INT1_SRC_A as usize {
+                registers[start] &= !IA;
+            }
+            Ok(())
+        }
+    }
+
+    fn sensor() -> Lsm303agr<FakeBus> {
+        let mut sensor = Lsm303agr::new(FakeBus::new());
+        sensor.init().unwrap();
+        sensor
+    }
+
+    fn words(x: i16, y: i16, z: i16) -> [u8; 6] {
+        let mut bytes = [0; 6];
+        bytes[..2].copy_from_slice(&x.to_le_bytes());
+        bytes[2..4].copy_from_slice(&y.to_le_bytes());
+        bytes[4..].copy_from_slice(&z.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_init_checks_both_sensors() {
+        let mut bus = FakeBus::new();
+        bus.mag[WHO_AM_I_M as usize] = 0x3d;
+        assert_eq!(
+            Lsm303agr::new(bus).init(),
+            Err(Error::UnknownDevice {
+                address: MAG_ADDRESS,
+                id: 0x3d
+            })
+        );
+
+        let mut bus = FakeBus::new();
+        bus.accel[WHO_AM_I_A as usize] = 0x5a;
+        let mut sensor = Lsm303agr::new(bus);
+        assert_eq!(
+            sensor.init(),
+            Err(Error::UnknownDevice {
+                address: ACCEL_ADDRESS,
+                id: 0x5a
+            })
+        );
+        // Nothing is configured in a device that isn't known
+        assert!(sensor.release().writes.is_empty());
+    }
+
+    #[test]
+    fn test_init_configures_both_sensors() {
+        let bus = sensor().release();
+        assert_eq!(bus.accel[CTRL_REG1_A as usize], 0x07);
+        assert_eq!(bus.accel[CTRL_REG3_A as usize], 0x00);
+        assert_eq!(bus.accel[CTRL_REG4_A as usize], 0x88);
+        assert_eq!(bus.accel[CTRL_REG6_A as usize], 0x02);
+        assert_eq!(bus.accel[INT1_CFG_A as usize], 0x00);
+        assert_eq!(bus.mag[CFG_REG_A_M as usize], 0x80);
+        assert_eq!(bus.mag[CFG_REG_C_M as usize], 0x10);
+    }
+
+    #[test]
+    fn test_rates_and_scales() {
+        let mut sensor = sensor();
+        sensor.set_accel_rate(AccelRate::Hz100).unwrap();
+        sensor.set_accel_scale(AccelScale::G8).unwrap();
+        sensor.set_mag_rate(MagRate::Hz50).unwrap();
+        let bus = sensor.release();
+        assert_eq!(bus.accel[CTRL_REG1_A as usize], 0x57);
+        assert_eq!(bus.accel[CTRL_REG4_A as usize], 0xa8);
+        assert_eq!(bus.mag[CFG_REG_A_M as usize], 0x88);
+
+        let mut sensor = Lsm303agr::new(bus);
+        sensor.set_accel_rate(AccelRate::Hz1344).unwrap();
+        assert_eq!(sensor.release().accel[CTRL_REG1_A as usize], 0x97);
+    }
+
+    #[test]
+    fn test_no_device_is_a_bus_error() {
+        let mut sensor = sensor();
+        assert_eq!(sensor.read(0x42, WHO_AM_I_A), Err(Error::Bus(Nack)));
+    }
+
+    #[test]
+    fn test_acceleration_in_milli_g() {
+        let mut bus = FakeBus::new();
+        // 1 g on x and against z at ±2 g, left justified
+        bus.accel[OUT_X_L_A as usize..][..6].copy_from_slice(&words(1020 << 4, 0, -1020 << 4));
+        bus.accel[STATUS_REG_A as usize] = ZYXDA;
+        let mut sensor = Lsm303agr::new(bus);
+        sensor.init().unwrap();
+        assert_eq!(sensor.accel_ready(), Ok(true));
+        assert_eq!(
+            sensor.acceleration(),
+            Ok(Vector {
+                x: 999,
+                y: 0,
+                z: -999
+            })
+        );
+
+        sensor.set_accel_scale(AccelScale::G16).unwrap();
+        assert_eq!(
+            sensor.acceleration(),
+            Ok(Vector {
+                x: 11954,
+                y: 0,
+                z: -11954
+            })
+        );
+        // The low four bits aren't part of the reading
+        let mut bus = sensor.release();
+        bus.accel[OUT_X_L_A as usize..][..6].copy_from_slice(&words(0x000f, 0x0010, -1));
+        let mut sensor = Lsm303agr::new(bus);
+        sensor.init().unwrap();
+        assert_eq!(sensor.acceleration().unwrap().to_string(), "(0, 0, 0)");
+    }
+
+    #[test]
+    fn test_magnetic_field_in_nanotesla() {
+        let mut bus = FakeBus::new();
+        bus.mag[OUTX_L_REG_M as usize..][..6].copy_from_slice(&words(100, -200, i16::MIN));
+        bus.mag[STATUS_REG_M as usize] = ZYXDA;
+        let mut sensor = Lsm303agr::new(bus);
+        sensor.init().unwrap();
+        assert_eq!(sensor.mag_ready(), Ok(true));
+        assert_eq!(
+            sensor.magnetic_field(),
+            Ok(Vector {
+                x: 15_000,
+                y: -30_000,
+                z: -4_915_200
+            })
+        );
+    }
+
+    #[test]
+    fn test_interrupts() {
+        let mut sensor = sensor();
+        sensor.set_accel_rate(AccelRate::Hz100).unwrap();
+        sensor
+            .set_interrupts(Interrupts {
+                data_ready: true,
+                motion: Some(Motion {
+                    threshold_mg: 1500,
+                    duration_ms: 50,
+                }),
+            })
+            .unwrap();
+        let bus = sensor.release();
+        assert_eq!(bus.accel[INT1_THS_A as usize], 93);
+        assert_eq!(bus.accel[INT1_DURATION_A as usize], 5);
+        assert_eq!(bus.accel[INT1_CFG_A as usize], 0x2a);
+        assert_eq!(bus.accel[CTRL_REG5_A as usize], 0x08);
+        assert_eq!(bus.accel[CTRL_REG3_A as usize], 0x50);
+
+        let mut sensor = Lsm303agr::new(bus);
+        sensor
+            .set_interrupts(Interrupts {
+                data_ready: true,
+                motion: None,
+            })
+            .unwrap();
+        let bus = sensor.release();
+        assert_eq!(bus.accel[INT1_CFG_A as usize], 0x00);
+        assert_eq!(bus.accel[CTRL_REG3_A as usize], 0x10);
+    }
+
+    #[test]
+    fn test_motion_limits_follow_the_scale_and_rate() {
+        let mut sensor = sensor();
+        sensor.set_accel_rate(AccelRate::Hz10).unwrap();
+        sensor
+            .set_interrupts(Interrupts {
+                data_ready: false,
+                motion: Some(Motion {
+                    threshold_mg: 1500,
+                    duration_ms: 1000,
+                }),
+            })
+            .unwrap();
+        sensor.set_accel_scale(AccelScale::G8).unwrap();
+        sensor.set_accel_rate(AccelRate::Hz400).unwrap();
+        let bus = sensor.release();
+        assert_eq!(bus.written(ACCEL_ADDRESS, INT1_THS_A), Some(24));
+        // Limited to what the register holds
+        assert_eq!(bus.written(ACCEL_ADDRESS, INT1_DURATION_A), Some(127));
+    }
+
+    #[test]
+    fn test_extreme_motion_limits() {
+        let mut sensor = sensor();
+        sensor.set_accel_rate(AccelRate::Hz1344).unwrap();
+        sensor
+            .set_interrupts(Interrupts {
+                data_ready: false,
+                motion: Some(Motion {
+                    threshold_mg: u32::MAX,
+                    duration_ms: u32::MAX,
+                }),
+            })
+            .unwrap();
+        sensor.set_accel_scale(AccelScale::G16).unwrap();
+        let bus = sensor.release();
+        assert_eq!(bus.written(ACCEL_ADDRESS, INT1_THS_A), Some(127));
+        assert_eq!(bus.written(ACCEL_ADDRESS, INT1_DURATION_A), Some(127));
+    }
+
+    #[test]
+    fn test_motion_detected_lets_go() {
+        let mut sensor = sensor();
+        sensor.i2c.accel[INT1_SRC_A as usize] = IA | 0b10_0000;
+        assert_eq!(sensor.motion_detected(), Ok(true));
+        assert_eq!(sensor.motion_detected(), Ok(false));
+    }
+
+    #[test]
+    fn test_error_display() {
+        let error: Error<Nack> = Error::UnknownDevice {
+            address: ACCEL_ADDRESS,
+            id: 0x5a,
+        };
+        assert_eq!(error.to_string(), "unknown device 0x5a at 0x19");
+        assert_eq!(Error::Bus(Nack).to_string(), "I2C bus error: Nack");
+    }
+}
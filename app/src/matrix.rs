@@ -0,0 +1,174 @@
+//! How each version of the micro:bit wires its LEDs to pins.
+//!
+//! Whatever the wiring, the display is a logical grid of `ROWS` by `COLS`
+//! pixels. The v2 is wired as it looks, with a pin for each of its five
+//! rows and five columns. The v1 is wired as three rows of nine columns,
+//! with two of the 27 crossings left without an LED.
+
+use crate::display::{COLS, ROWS};
+
+/// For each row and column pin of a board's matrix, the logical row and
+/// column of the pixel lit where they cross, if any.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Layout<const R: usize, const C: usize> {
+    pub pixels: [[Option<(usize, usize)>; C]; R],
+}
+
+impl<const R: usize, const C: usize> Layout<R, C> {
+    /// The row and column pins that light a logical pixel.
+    pub fn position(&self, row: usize, col: usize) -> Option<(usize, usize)> {
+        self.pixels.iter().enumerate().find_map(|(r, pixels)| {
+            pixels
+                .iter()
+                .position(|pixel| *pixel == Some((row, col)))
+                .map(|c| (r, c))
+        })
+    }
+
+    /// The time each row pin should be lit for, in microseconds, so that
+    /// the whole matrix is refreshed at the given rate.
+    pub const fn row_period_us(&self, frame_rate_hz: u32) -> u32 {
+        1_000_000 / (frame_rate_hz * R as u32)
+    }
+}
+
+pub const V1_ROWS: usize = 3;
+pub const V1_COLS: usize = 9;
+
+/// The micro:bit v1, from its schematic.
+pub const V1: Layout<V1_ROWS, V1_COLS> = Layout {
+    pixels: [
+        [
+            Some((0, 0)),
+            Some((0, 2)),
+            Some((0, 4)),
+            Some((3, 4)),
+            Some((3, 3)),
+            Some((3, 2)),
+            Some((3, 1)),
+            Some((3, 0)),
+            Some((2, 1)),
+        ],
+        [
+            Some((2, 4)),
+            Some((2, 0)),
+            Some((2, 2)),
+            Some((0, 1)),
+            Some((0, 3)),
+            Some((4, 3)),
+            Some((4, 1)),
+            None,
+            None,
+        ],
+        [
+            Some((4, 2)),
+            Some((4, 4)),
+            Some((4, 0)),
+            Some((1, 0)),
+            Some((1, 1)),
+            Some((1, 2)),
+            Some((1, 3)),
+            Some((1, 4)),
+            Some((2, 3)),
+        ],
+    ],
+};
+
+/// The micro:bit v2, which has a pin for each row and column.
+pub const V2: Layout<ROWS, COLS> = Layout {
+    pixels: [
+        [
+            Some((0, 0)),
+            Some((0, 1)),
+            Some((0, 2)),
+            Some((0, 3)),
+            Some((0, 4)),
+        ],
+        [
+            Some((1, 0)),
+            Some((1, 1)),
+            Some((1, 2)),
+            Some((1, 3)),
+            Some((1, 4)),
+        ],
+        [
+            Some((2, 0)),
+            Some((2, 1)),
+            Some((2, 2)),
+            Some((2, 3)),
+            Some((2, 4)),
+        ],
+        [
+            Some((3, 0)),
+            Some((3, 1)),
+            Some((3, 2)),
+            Some((3, 3)),
+            Some((3, 4)),
+        ],
+        [
+            Some((4, 0)),
+            Some((4, 1)),
+            Some((4, 2)),
+            Some((4, 3)),
+            Some((4, 4)),
+        ],
+    ],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_covers_every_pixel_once<const R: usize, const C: usize>(layout: &Layout<R, C>) {
+        let mut seen = [[0; COLS]; ROWS];
+        for (row, col) in layout.pixels.iter().flatten().flatten() {
+            seen[*row][*col] += 1;
+        }
+        assert_eq!(seen, [[1; COLS]; ROWS]);
+    }
+
+    #[test]
+    fn test_every_pixel_is_wired_once() {
+        assert_covers_every_pixel_once(&V1);
+        assert_covers_every_pixel_once(&V2);
+    }
+
+    #[test]
+    fn test_v1_has_two_unused_crossings() {
+        let unused: Vec<(usize, usize)> = (0..V1_ROWS)
+            .flat_map(|r| (0..V1_COLS).map(move |c| (r, c)))
+            .filter(|(r, c)| V1.pixels[*r][*c].is_none())
+            .collect();
+        assert_eq!(unused, [(1, 7), (1, 8)]);
+    }
+
+    #[test]
+    fn test_v1_positions() {
+        // The corners, then the middle
+        assert_eq!(V1.position(0, 0), Some((0, 0)));
+        assert_eq!(V1.position(0, 4), Some((0, 2)));
+        assert_eq!(V1.position(4, 0), Some((2, 2)));
+        assert_eq!(V1.position(4, 4), Some((2, 1)));
+        assert_eq!(V1.position(2, 2), Some((1, 2)));
+        // The second row is all on the third row pin
+        for col in 0..COLS {
+            assert_eq!(V1.position(1, col), Some((2, 3 + col)));
+        }
+        assert_eq!(V1.position(5, 0), None);
+    }
+
+    #[test]
+    fn test_v2_is_as_it_looks() {
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                assert_eq!(V2.position(row, col), Some((row, col)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_row_period() {
+        assert_eq!(V1.row_period_us(100), 3_333);
+        assert_eq!(V2.row_period_us(100), 2_000);
+    }
+}
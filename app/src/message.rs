@@ -0,0 +1,553 @@
+//! The requests a host can make of the board, and the board's responses,
+//! for carrying in frames.
+//!
+//! Every message starts with a header of the protocol version, a sequence
+//! number chosen by the host and echoed back in the response, and the
+//! message's type. The body follows, with numbers in little endian and text
+//! prefixed by its length.
+//!
+//! | Offset | Size | Field    |
+//! |--------|------|----------|
+//! | 0      | 1    | version  |
+//! | 1      | 2    | sequence |
+//! | 3      | 1    | type     |
+//! | 4      |      | body     |
+
+use crate::display::{Image, COLS, MAX_LEVEL, ROWS};
+
+/// Bumped whenever a message changes in a way that an older peer would
+/// get wrong.
+pub const VERSION: u8 = 1;
+
+/// The longest text that can be sent to scroll.
+pub const MAX_TEXT_LEN: usize = 64;
+
+/// The longest any message can encode to.
+pub const MAX_MESSAGE_LEN: usize = HEADER_LEN + 1 + MAX_TEXT_LEN;
+
+const HEADER_LEN: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Request<'a> {
+    Ping,
+    GetVersion,
+    GetButtons,
+    GetTemperature,
+    SetPixel { row: u8, col: u8, level: u8 },
+    SetImage(Image),
+    ScrollText(&'a str),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Response {
+    Pong,
+    Version {
+        major: u8,
+        minor: u8,
+        patch: u8,
+    },
+    Buttons {
+        a: bool,
+        b: bool,
+    },
+    /// The temperature of the chip, in hundredths of a degree Celsius.
+    Temperature(i32),
+    /// The request has been carried out.
+    Done,
+    /// The request could not be carried out.
+    Rejected(Rejection),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    UnsupportedVersion,
+    UnknownType,
+    Malformed,
+    InvalidArgument,
+}
+
+/// A request or response, along with its sequence number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Message<T> {
+    pub seq: u16,
+    pub body: T,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodeError {
+    BufferTooSmall,
+    /// Text is longer than `MAX_TEXT_LEN`.
+    TextTooLong,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    UnsupportedVersion(u8),
+    UnknownType(u8),
+    /// The message ended before its body did.
+    Truncated,
+    /// There is more to the message than its body.
+    TrailingBytes,
+    /// A field holds a value that it cannot, such as a level above
+    /// `MAX_LEVEL` or text that is not UTF-8.
+    InvalidValue,
+}
+
+impl From<DecodeError> for Rejection {
+    fn from(error: DecodeError) -> Self {
+        match error {
+            DecodeError::UnsupportedVersion(_) => Rejection::UnsupportedVersion,
+            DecodeError::UnknownType(_) => Rejection::UnknownType,
+            DecodeError::Truncated | DecodeError::TrailingBytes => Rejection::Malformed,
+            DecodeError::InvalidValue => Rejection::InvalidArgument,
+        }
+    }
+}
+
+/// The sequence number of a message that may not otherwise decode, so that
+/// it can still be answered.
+pub fn sequence(bytes: &[u8]) -> Option<u16> {
+    match bytes {
+        [_, low, high, ..] => Some(u16::from_le_bytes([*low, *high])),
+        _ => None,
+    }
+}
+
+mod tag {
+    pub const PING: u8 = 0x01;
+    pub const GET_VERSION: u8 = 0x02;
+    pub const GET_BUTTONS: u8 = 0x03;
+    pub const GET_TEMPERATURE: u8 = 0x04;
+    pub const SET_PIXEL: u8 = 0x10;
+    pub const SET_IMAGE: u8 = 0x11;
+    pub const SCROLL_TEXT: u8 = 0x12;
+
+    pub const PONG: u8 = 0x81;
+    pub const VERSION: u8 = 0x82;
+    pub const BUTTONS: u8 = 0x83;
+    pub const TEMPERATURE: u8 = 0x84;
+    pub const DONE: u8 = 0x90;
+    pub const REJECTED: u8 = 0xff;
+}
+
+impl<'a> Message<Request<'a>> {
+    pub fn encode(&self, buffer: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut writer = Writer::new(buffer);
+        match self.body {
+            Request::Ping => writer.header(self.seq, tag::PING)?,
+            Request::GetVersion => writer.header(self.seq, tag::GET_VERSION)?,
+            Request::GetButtons => writer.header(self.seq, tag::GET_BUTTONS)?,
+            Request::GetTemperature => writer.header(self.seq, tag::GET_TEMPERATURE)?,
+            Request::SetPixel { row, col, level } => {
+                writer.header(self.seq, tag::SET_PIXEL)?;
+                writer.bytes(&[row, col, level])?;
+            }
+            Request::SetImage(image) => {
+                writer.header(self.seq, tag::SET_IMAGE)?;
+                for row in image.pixels() {
+                    writer.bytes(row)?;
+                }
+            }
+            Request::ScrollText(text) => {
+                writer.header(self.seq, tag::SCROLL_TEXT)?;
+                writer.text(text)?;
+            }
+        }
+        Ok(writer.len)
+    }
+
+    pub fn decode(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let (seq, message_type) = reader.header()?;
+        let body = match message_type {
+            tag::PING => Request::Ping,
+            tag::GET_VERSION => Request::GetVersion,
+            tag::GET_BUTTONS => Request::GetButtons,
+            tag::GET_TEMPERATURE => Request::GetTemperature,
+            tag::SET_PIXEL => {
+                let [row, col, level] = reader.array()?;
+                if usize::from(row) >= ROWS || usize::from(col) >= COLS || level > MAX_LEVEL {
+                    return Err(DecodeError::InvalidValue);
+                }
+                Request::SetPixel { row, col, level }
+            }
+            tag::SET_IMAGE => {
+                let mut pixels = [[0; COLS]; ROWS];
+                for row in pixels.iter_mut() {
+                    *row = reader.array()?;
+                    if row.iter().any(|level| *level > MAX_LEVEL) {
+                        return Err(DecodeError::InvalidValue);
+                    }
+                }
+                Request::SetImage(Image::new(pixels))
+            }
+            tag::SCROLL_TEXT => Request::ScrollText(reader.text()?),
+            other => return Err(DecodeError::UnknownType(other)),
+        };
+        reader.end()?;
+        Ok(Self { seq, body })
+    }
+}
+
+impl Message<Response> {
+    pub fn encode(&self, buffer: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut writer = Writer::new(buffer);
+        match self.body {
+            Response::Pong => writer.header(self.seq, tag::PONG)?,
+            Response::Version {
+                major,
+                minor,
+                patch,
+            } => {
+                writer.header(self.seq, tag::VERSION)?;
+                writer.bytes(&[major, minor, patch])?;
+            }
+            Response::Buttons { a, b } => {
+                writer.header(self.seq, tag::BUTTONS)?;
+                writer.bytes(&[u8::from(a) | u8::from(b) << 1])?;
+            }
+            Response::Temperature(centi_celsius) => {
+                writer.header(self.seq, tag::TEMPERATURE)?;
+                writer.bytes(&centi_celsius.to_le_bytes())?;
+            }
+            Response::Done => writer.header(self.seq, tag::DONE)?,
+            Response::Rejected(rejection) => {
+                writer.header(self.seq, tag::REJECTED)?;
+                writer.bytes(&[rejection as u8])?;
+            }
+        }
+        Ok(writer.len)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let (seq, message_type) = reader.header()?;
+        let body = match message_type {
+            tag::PONG => Response::Pong,
+            tag::VERSION => {
+                let [major, minor, patch] = reader.array()?;
+                Response::Version {
+                    major,
+                    minor,
+                    patch,
+                }
+            }
+            tag::BUTTONS => {
+                let [bits] = reader.array()?;
+                if bits > 0b11 {
+                    return Err(DecodeError::InvalidValue);
+                }
+                Response::Buttons {
+                    a: bits & 0b01 != 0,
+                    b: bits & 0b10 != 0,
+                }
+            }
+            tag::TEMPERATURE => Response::Temperature(i32::from_le_bytes(reader.array()?)),
+            tag::DONE => Response::Done,
+            tag::REJECTED => {
+                let [rejection] = reader.array()?;
+                Response::Rejected(match rejection {
+                    0 => Rejection::UnsupportedVersion,
+                    1 => Rejection::UnknownType,
+                    2 => Rejection::Malformed,
+                    3 => Rejection::InvalidArgument,
+                    _ => return Err(DecodeError::InvalidValue),
+                })
+            }
+            other => return Err(DecodeError::UnknownType(other)),
+        };
+        reader.end()?;
+        Ok(Self { seq, body })
+    }
+}
+
+struct Writer<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    fn header(&mut self, seq: u16, message_type: u8) -> Result<(), EncodeError> {
+        let [low, high] = seq.to_le_bytes();
+        self.bytes(&[VERSION, low, high, message_type])
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
+        let end = self.len + bytes.len();
+        self.buffer
+            .get_mut(self.len..end)
+            .ok_or(EncodeError::BufferTooSmall)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str) -> Result<(), EncodeError> {
+        if text.len() > MAX_TEXT_LEN {
+            return Err(EncodeError::TextTooLong);
+        }
+        self.bytes(&[text.len() as u8])?;
+        self.bytes(text.as_bytes())
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// The sequence number and message type, once the version is checked.
+    fn header(&mut self) -> Result<(u16, u8), DecodeError> {
+        let [version] = self.array()?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let seq = u16::from_le_bytes(self.array()?);
+        let [message_type] = self.array()?;
+        Ok((seq, message_type))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < len {
+            return Err(DecodeError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn text(&mut self) -> Result<&'a str, DecodeError> {
+        let [len] = self.array()?;
+        if usize::from(len) > MAX_TEXT_LEN {
+            return Err(DecodeError::InvalidValue);
+        }
+        core::str::from_utf8(self.take(usize::from(len))?).map_err(|_| DecodeError::InvalidValue)
+    }
+
+    fn end(&self) -> Result<(), DecodeError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(DecodeError::TrailingBytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_request(request: Request) -> Vec<u8> {
+        let mut buffer = [0; MAX_MESSAGE_LEN];
+        let message = Message {
+            seq: 0x1234,
+            body: request,
+        };
+        let len = message.encode(&mut buffer).unwrap();
+        buffer[..len].to_vec()
+    }
+
+    fn encode_response(response: Response) -> Vec<u8> {
+        let mut buffer = [0; MAX_MESSAGE_LEN];
+        let message = Message {
+            seq: 0xbeef,
+            body: response,
+        };
+        let len = message.encode(&mut buffer).unwrap();
+        buffer[..len].to_vec()
+    }
+
+    #[test]
+    fn test_requests_round_trip() {
+        let long_text = "x".repeat(MAX_TEXT_LEN);
+        let requests = [
+            Request::Ping,
+            Request::GetVersion,
+            Request::GetButtons,
+            Request::GetTemperature,
+            Request::SetPixel {
+                row: 4,
+                col: 0,
+                level: 9,
+            },
+            Request::SetImage(Image::from_bits(&[0b10101, 0, 0b11111, 0, 0b00100], 7)),
+            Request::ScrollText("Hello, \u{b0}C"),
+            Request::ScrollText(""),
+            Request::ScrollText(&long_text),
+        ];
+        for request in requests.iter() {
+            let bytes = encode_request(*request);
+            assert_eq!(
+                Message::<Request>::decode(&bytes),
+                Ok(Message {
+                    seq: 0x1234,
+                    body: *request
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_responses_round_trip() {
+        let responses = [
+            Response::Pong,
+            Response::Version {
+                major: 1,
+                minor: 2,
+                patch: 3,
+            },
+            Response::Buttons { a: true, b: false },
+            Response::Buttons { a: false, b: true },
+            Response::Temperature(-1_025),
+            Response::Done,
+            Response::Rejected(Rejection::UnsupportedVersion),
+            Response::Rejected(Rejection::UnknownType),
+            Response::Rejected(Rejection::Malformed),
+            Response::Rejected(Rejection::InvalidArgument),
+        ];
+        for response in responses.iter() {
+            let bytes = encode_response(*response);
+            assert_eq!(
+                Message::<Response>::decode(&bytes),
+                Ok(Message {
+                    seq: 0xbeef,
+                    body: *response
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(
+            encode_request(Request::Ping),
+            vec![VERSION, 0x34, 0x12, 0x01]
+        );
+        assert_eq!(
+            encode_request(Request::ScrollText("Hi")),
+            vec![VERSION, 0x34, 0x12, 0x12, 2, b'H', b'i']
+        );
+        assert_eq!(
+            encode_response(Response::Temperature(2_150)),
+            vec![VERSION, 0xef, 0xbe, 0x84, 0x66, 0x08, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_unknown_types_are_errors() {
+        assert_eq!(
+            Message::<Request>::decode(&[VERSION, 0, 0, 0x7f]),
+            Err(DecodeError::UnknownType(0x7f))
+        );
+        // Responses are not requests, nor the other way round
+        assert_eq!(
+            Message::<Request>::decode(&encode_response(Response::Pong)),
+            Err(DecodeError::UnknownType(0x81))
+        );
+        assert_eq!(
+            Message::<Response>::decode(&encode_request(Request::Ping)),
+            Err(DecodeError::UnknownType(0x01))
+        );
+    }
+
+    #[test]
+    fn test_other_versions_are_errors() {
+        let mut bytes = encode_request(Request::Ping);
+        bytes[0] = VERSION + 1;
+        assert_eq!(
+            Message::<Request>::decode(&bytes),
+            Err(DecodeError::UnsupportedVersion(VERSION + 1))
+        );
+        assert_eq!(sequence(&bytes), Some(0x1234));
+    }
+
+    #[test]
+    fn test_every_truncation_is_an_error() {
+        let bytes = encode_request(Request::SetImage(Image::blank()));
+        for len in 0..bytes.len() {
+            assert_eq!(
+                Message::<Request>::decode(&bytes[..len]),
+                Err(DecodeError::Truncated)
+            );
+        }
+        let mut long = bytes.clone();
+        long.push(0);
+        assert_eq!(
+            Message::<Request>::decode(&long),
+            Err(DecodeError::TrailingBytes)
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_values_are_errors() {
+        let mut bytes = encode_request(Request::SetPixel {
+            row: 0,
+            col: 0,
+            level: 0,
+        });
+        for (at, value) in [(4, ROWS as u8), (5, COLS as u8), (6, MAX_LEVEL + 1)].iter() {
+            let mut bad = bytes.clone();
+            bad[*at] = *value;
+            assert_eq!(
+                Message::<Request>::decode(&bad),
+                Err(DecodeError::InvalidValue)
+            );
+        }
+
+        bytes = encode_request(Request::ScrollText("ab"));
+        bytes[5] = 0xff;
+        assert_eq!(
+            Message::<Request>::decode(&bytes),
+            Err(DecodeError::InvalidValue)
+        );
+
+        assert_eq!(
+            Message::<Response>::decode(&[VERSION, 0, 0, 0xff, 9]),
+            Err(DecodeError::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn test_encoding_limits() {
+        let long_text = "x".repeat(MAX_TEXT_LEN + 1);
+        let message = Message {
+            seq: 0,
+            body: Request::ScrollText(&long_text),
+        };
+        assert_eq!(message.encode(&mut [0; 256]), Err(EncodeError::TextTooLong));
+        let message = Message {
+            seq: 0,
+            body: Request::SetImage(Image::blank()),
+        };
+        assert_eq!(
+            message.encode(&mut [0; 10]),
+            Err(EncodeError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_rejections_for_decode_errors() {
+        assert_eq!(
+            Rejection::from(DecodeError::UnknownType(7)),
+            Rejection::UnknownType
+        );
+        assert_eq!(
+            Rejection::from(DecodeError::Truncated),
+            Rejection::Malformed
+        );
+    }
+}
@@ -0,0 +1,381 @@
+//! A fixed capacity, lock-free queue for passing values from one producer
+//! to one consumer, such as from an interrupt handler to the main loop.
+//!
+//! The queue is split once into a `Producer` and a `Consumer`, which are
+//! the only ways in, so there can never be more than one of each. When the
+//! queue is full new values are dropped, and counted, rather than blocking
+//! the producer.
+//!
+//! The head and tail count through `0..2 * N`, wrapping explicitly, so that
+//! a full queue can be told from an empty one, and so that the slot they
+//! fall on stays right whatever `N` is. Counting freely and wrapping at
+//! `usize::MAX` would skip slots there unless `N` were a power of two.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+pub struct Queue<T, const N: usize> {
+    buffer: UnsafeCell<MaybeUninit<[T; N]>>,
+    /// Where the next value is taken from, only advanced by the consumer.
+    head: AtomicUsize,
+    /// Where the next value is added, only advanced by the producer.
+    tail: AtomicUsize,
+    dropped: AtomicU32,
+    split: AtomicBool,
+}
+
+// The producer and consumer only ever touch slots that the other has
+// finished with, as published through `head` and `tail`.
+unsafe impl<T: Send, const N: usize> Sync for Queue<T, N> {}
+
+impl<T, const N: usize> Queue<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            buffer: UnsafeCell::new(MaybeUninit::uninit()),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicU32::new(0),
+            split: AtomicBool::new(false),
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Hand out the two ends of the queue. This only succeeds once.
+    ///
+    /// Cores without atomic read-modify-write, such as the nRF51's
+    /// Cortex-M0, can't tell two splits racing each other apart, so there
+    /// the queue must be split from one context only, such as at startup.
+    pub fn split(&self) -> Option<(Producer<'_, T, N>, Consumer<'_, T, N>)> {
+        if self.claim() {
+            Some((Producer { queue: self }, Consumer { queue: self }))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(target_has_atomic = "8")]
+    fn claim(&self) -> bool {
+        !self.split.swap(true, Ordering::AcqRel)
+    }
+
+    #[cfg(not(target_has_atomic = "8"))]
+    fn claim(&self) -> bool {
+        if self.split.load(Ordering::Acquire) {
+            return false;
+        }
+        self.split.store(true, Ordering::Release);
+        true
+    }
+
+    /// The number of values dropped because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        Self::distance(head, tail)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Only called by the producer, so the count needs no read-modify-write.
+    fn count_dropped(&self, count: u32) {
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        self.dropped
+            .store(dropped.wrapping_add(count), Ordering::Relaxed);
+    }
+
+    /// The index `count` values on from `index`, where `count` is at most
+    /// `N`.
+    fn advance(index: usize, count: usize) -> usize {
+        (index + count) % (2 * N)
+    }
+
+    /// How many values lie from `head` up to `tail`.
+    fn distance(head: usize, tail: usize) -> usize {
+        (tail + 2 * N - head) % (2 * N)
+    }
+
+    fn slot(&self, index: usize) -> *mut T {
+        unsafe { (*self.buffer.get()).as_mut_ptr().cast::<T>().add(index % N) }
+    }
+
+    /// A queue whose head and tail start as if `index` values had already
+    /// passed through it.
+    #[cfg(test)]
+    fn starting_at(index: usize) -> Self {
+        let queue = Self::new();
+        let index = index % (2 * N);
+        queue.head.store(index, Ordering::Relaxed);
+        queue.tail.store(index, Ordering::Relaxed);
+        queue
+    }
+}
+
+impl<T, const N: usize> Default for Queue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for Queue<T, N> {
+    fn drop(&mut self) {
+        let head = *self.head.get_mut();
+        let tail = *self.tail.get_mut();
+        let mut index = head;
+        while index != tail {
+            unsafe { ptr::drop_in_place(self.slot(index)) };
+            index = Self::advance(index, 1);
+        }
+    }
+}
+
+pub struct Producer<'a, T, const N: usize> {
+    queue: &'a Queue<T, N>,
+}
+
+impl<'a, T, const N: usize> Producer<'a, T, N> {
+    /// Add a value to the back of the queue, handing it back if the queue
+    /// is full. Either way, the queue never blocks.
+    pub fn enqueue(&mut self, value: T) -> Result<(), T> {
+        let queue = self.queue;
+        let tail = queue.tail.load(Ordering::Relaxed);
+        let head = queue.head.load(Ordering::Acquire);
+        if Queue::<T, N>::distance(head, tail) >= N {
+            queue.count_dropped(1);
+            return Err(value);
+        }
+        unsafe { ptr::write(queue.slot(tail), value) };
+        queue
+            .tail
+            .store(Queue::<T, N>::advance(tail, 1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Add as many values from the front of `values` as there is room for,
+    /// returning how many were taken. The rest are dropped and counted.
+    pub fn enqueue_slice(&mut self, values: &[T]) -> usize
+    where
+        T: Copy,
+    {
+        let queue = self.queue;
+        let tail = queue.tail.load(Ordering::Relaxed);
+        let head = queue.head.load(Ordering::Acquire);
+        let taken = values.len().min(N - Queue::<T, N>::distance(head, tail));
+        for (offset, value) in values[..taken].iter().enumerate() {
+            let index = Queue::<T, N>::advance(tail, offset);
+            unsafe { ptr::write(queue.slot(index), *value) };
+        }
+        queue
+            .tail
+            .store(Queue::<T, N>::advance(tail, taken), Ordering::Release);
+        let dropped = (values.len() - taken) as u32;
+        if dropped > 0 {
+            queue.count_dropped(dropped);
+        }
+        taken
+    }
+}
+
+pub struct Consumer<'a, T, const N: usize> {
+    queue: &'a Queue<T, N>,
+}
+
+impl<'a, T, const N: usize> Consumer<'a, T, N> {
+    /// Take the value at the front of the queue, if there is one.
+    pub fn dequeue(&mut self) -> Option<T> {
+        let queue = self.queue;
+        let head = queue.head.load(Ordering::Relaxed);
+        let tail = queue.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        let value = unsafe { ptr::read(queue.slot(head)) };
+        queue
+            .head
+            .store(Queue::<T, N>::advance(head, 1), Ordering::Release);
+        Some(value)
+    }
+
+    /// The number of values dropped because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.queue.dropped()
+    }
+}
+
+impl<'a, T, const N: usize> Iterator for Consumer<'a, T, N> {
+    type Item = T;
+
+    /// Drains the queue, ending when it is empty for now.
+    fn next(&mut self) -> Option<T> {
+        self.dequeue()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn test_first_in_first_out() {
+        let queue: Queue<u32, 4> = Queue::new();
+        let (mut producer, mut consumer) = queue.split().unwrap();
+        assert_eq!(consumer.dequeue(), None);
+        producer.enqueue(1).unwrap();
+        producer.enqueue(2).unwrap();
+        assert_eq!(consumer.dequeue(), Some(1));
+        producer.enqueue(3).unwrap();
+        assert_eq!(consumer.by_ref().collect::<Vec<_>>(), vec![2, 3]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_splits_only_once() {
+        let queue: Queue<u32, 4> = Queue::new();
+        assert!(queue.split().is_some());
+        assert!(queue.split().is_none());
+    }
+
+    #[test]
+    fn test_full_queue_drops_and_counts() {
+        let queue: Queue<u32, 2> = Queue::new();
+        let (mut producer, mut consumer) = queue.split().unwrap();
+        producer.enqueue(1).unwrap();
+        producer.enqueue(2).unwrap();
+        assert_eq!(producer.enqueue(3), Err(3));
+        assert_eq!(producer.enqueue(4), Err(4));
+        assert_eq!(consumer.dropped(), 2);
+        assert_eq!(queue.len(), 2);
+        assert_eq!(consumer.dequeue(), Some(1));
+        producer.enqueue(5).unwrap();
+        assert_eq!(consumer.by_ref().collect::<Vec<_>>(), vec![2, 5]);
+    }
+
+    #[test]
+    fn test_enqueue_slice_takes_what_fits() {
+        let queue: Queue<u8, 4> = Queue::new();
+        let (mut producer, mut consumer) = queue.split().unwrap();
+        assert_eq!(producer.enqueue_slice(b"ab"), 2);
+        assert_eq!(consumer.dequeue(), Some(b'a'));
+        assert_eq!(producer.enqueue_slice(b"cdefg"), 3);
+        assert_eq!(consumer.dropped(), 2);
+        assert_eq!(producer.enqueue_slice(b""), 0);
+        assert_eq!(consumer.by_ref().collect::<Vec<_>>(), b"bcde".to_vec());
+        assert_eq!(producer.enqueue_slice(b"hi"), 2);
+        assert_eq!(consumer.by_ref().collect::<Vec<_>>(), b"hi".to_vec());
+    }
+
+    #[test]
+    fn test_wraps_around_the_buffer() {
+        let queue: Queue<usize, 3> = Queue::new();
+        let (mut producer, mut consumer) = queue.split().unwrap();
+        for value in 0..100 {
+            producer.enqueue(value).unwrap();
+            assert_eq!(consumer.dequeue(), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_indices_near_usize_max() {
+        for start in [usize::MAX - 7, usize::MAX - 1, usize::MAX].iter() {
+            let queue: Queue<usize, 3> = Queue::starting_at(*start);
+            let (mut producer, mut consumer) = queue.split().unwrap();
+            let mut expected = std::collections::VecDeque::new();
+            // Fill to each level in turn, then drain, across many wraps
+            for (round, value) in (0..60).enumerate() {
+                if producer.enqueue(value).is_ok() {
+                    expected.push_back(value);
+                }
+                assert_eq!(queue.len(), expected.len());
+                if round % 4 == 3 {
+                    assert_eq!(
+                        consumer.by_ref().collect::<Vec<_>>(),
+                        Vec::from(expected.clone())
+                    );
+                    expected.clear();
+                } else if round % 5 == 0 {
+                    assert_eq!(consumer.dequeue(), expected.pop_front());
+                }
+            }
+            assert_eq!(consumer.by_ref().collect::<Vec<_>>(), Vec::from(expected));
+            assert!(queue.dropped() > 0);
+        }
+    }
+
+    #[test]
+    fn test_remaining_values_are_dropped_with_the_queue() {
+        let value = Rc::new(());
+        {
+            let queue: Queue<Rc<()>, 4> = Queue::new();
+            let (mut producer, _) = queue.split().unwrap();
+            producer.enqueue(value.clone()).unwrap();
+            producer.enqueue(value.clone()).unwrap();
+            assert_eq!(Rc::strong_count(&value), 3);
+        }
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    /// A producer thread stands in for an interrupt handler preempting the
+    /// consumer at arbitrary points.
+    #[test]
+    fn test_concurrent_producer_and_consumer() {
+        const COUNT: u64 = 20_000;
+        let queue: Queue<u64, 8> = Queue::new();
+        let (mut producer, mut consumer) = queue.split().unwrap();
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                for value in 0..COUNT {
+                    let mut value = value;
+                    while let Err(rejected) = producer.enqueue(value) {
+                        value = rejected;
+                        thread::yield_now();
+                    }
+                }
+            });
+            let mut expected = 0;
+            while expected < COUNT {
+                match consumer.dequeue() {
+                    Some(value) => {
+                        assert_eq!(value, expected);
+                        expected += 1;
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+        });
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_drops_are_all_accounted_for() {
+        const COUNT: u64 = 20_000;
+        let queue: Queue<u64, 4> = Queue::new();
+        let (mut producer, mut consumer) = queue.split().unwrap();
+        let received = thread::scope(|scope| {
+            let producing = scope.spawn(move || {
+                for value in 0..COUNT {
+                    let _ = producer.enqueue(value);
+                }
+            });
+            let mut received = Vec::new();
+            while !producing.is_finished() || !queue.is_empty() {
+                received.extend(consumer.by_ref());
+                thread::yield_now();
+            }
+            received
+        });
+        assert!(received.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(received.len() as u64 + u64::from(queue.dropped()), COUNT);
+    }
+}
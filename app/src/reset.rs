@@ -0,0 +1,151 @@
+//! Why the board last started, from the RESETREAS register. The nRF51's
+//! has the same bits as the nRF52's, less those for NFC and VBUS.
+//!
+//! Each bit of the register is set by a kind of reset and stays set until
+//! cleared, so the firmware should clear it once read. With no bits set,
+//! the board was powered on, or browned out.
+
+use core::fmt;
+
+const RESETPIN: u32 = 1 << 0;
+const DOG: u32 = 1 << 1;
+const SREQ: u32 = 1 << 2;
+const LOCKUP: u32 = 1 << 3;
+const OFF: u32 = 1 << 16;
+const LPCOMP: u32 = 1 << 17;
+const DIF: u32 = 1 << 18;
+const NFC: u32 = 1 << 19;
+const VBUS: u32 = 1 << 20;
+
+/// What woke the board from System OFF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WakeSource {
+    Gpio,
+    Lpcomp,
+    DebugInterface,
+    Nfc,
+    Vbus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetReason {
+    PowerOn,
+    Pin,
+    Watchdog,
+    SoftReset,
+    Lockup,
+    WakeFromOff(WakeSource),
+    /// Bits that this doesn't know about.
+    Unknown(u32),
+}
+
+/// Resets that leave their mark on RESETREAS, the most telling first.
+const REASONS: [(u32, ResetReason); 9] = [
+    (DOG, ResetReason::Watchdog),
+    (LOCKUP, ResetReason::Lockup),
+    (SREQ, ResetReason::SoftReset),
+    (RESETPIN, ResetReason::Pin),
+    (OFF, ResetReason::WakeFromOff(WakeSource::Gpio)),
+    (LPCOMP, ResetReason::WakeFromOff(WakeSource::Lpcomp)),
+    (DIF, ResetReason::WakeFromOff(WakeSource::DebugInterface)),
+    (NFC, ResetReason::WakeFromOff(WakeSource::Nfc)),
+    (VBUS, ResetReason::WakeFromOff(WakeSource::Vbus)),
+];
+
+impl ResetReason {
+    /// Decode the RESETREAS register. Should more than one bit be set, such
+    /// as when it wasn't cleared after the last reset, the most telling
+    /// wins.
+    pub fn from_bits(bits: u32) -> Self {
+        if bits == 0 {
+            return ResetReason::PowerOn;
+        }
+        REASONS
+            .iter()
+            .find(|(bit, _)| bits & bit != 0)
+            .map_or(ResetReason::Unknown(bits), |(_, reason)| *reason)
+    }
+}
+
+impl fmt::Display for WakeSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WakeSource::Gpio => "GPIO",
+            WakeSource::Lpcomp => "LPCOMP",
+            WakeSource::DebugInterface => "the debug interface",
+            WakeSource::Nfc => "NFC",
+            WakeSource::Vbus => "VBUS",
+        })
+    }
+}
+
+impl fmt::Display for ResetReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResetReason::PowerOn => f.write_str("power on"),
+            ResetReason::Pin => f.write_str("reset pin"),
+            ResetReason::Watchdog => f.write_str("watchdog"),
+            ResetReason::SoftReset => f.write_str("soft reset"),
+            ResetReason::Lockup => f.write_str("CPU lockup"),
+            ResetReason::WakeFromOff(source) => write!(f, "wake from System OFF by {}", source),
+            ResetReason::Unknown(bits) => write!(f, "unknown ({:#010x})", bits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_bits_is_power_on() {
+        assert_eq!(ResetReason::from_bits(0), ResetReason::PowerOn);
+    }
+
+    #[test]
+    fn test_each_bit() {
+        for (bit, reason) in REASONS.iter() {
+            assert_eq!(ResetReason::from_bits(*bit), *reason);
+        }
+        assert_eq!(ResetReason::from_bits(0x1), ResetReason::Pin);
+        assert_eq!(ResetReason::from_bits(0x2), ResetReason::Watchdog);
+        assert_eq!(ResetReason::from_bits(0x4), ResetReason::SoftReset);
+        assert_eq!(ResetReason::from_bits(0x8), ResetReason::Lockup);
+        assert_eq!(
+            ResetReason::from_bits(0x1_0000),
+            ResetReason::WakeFromOff(WakeSource::Gpio)
+        );
+    }
+
+    #[test]
+    fn test_the_most_telling_bit_wins() {
+        assert_eq!(ResetReason::from_bits(SREQ | DOG), ResetReason::Watchdog);
+        assert_eq!(
+            ResetReason::from_bits(RESETPIN | LOCKUP),
+            ResetReason::Lockup
+        );
+        assert_eq!(ResetReason::from_bits(OFF | RESETPIN), ResetReason::Pin);
+    }
+
+    #[test]
+    fn test_unknown_bits() {
+        assert_eq!(
+            ResetReason::from_bits(1 << 31),
+            ResetReason::Unknown(1 << 31)
+        );
+        assert_eq!(ResetReason::from_bits(DOG | 1 << 31), ResetReason::Watchdog);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(ResetReason::PowerOn.to_string(), "power on");
+        assert_eq!(
+            ResetReason::WakeFromOff(WakeSource::Nfc).to_string(),
+            "wake from System OFF by NFC"
+        );
+        assert_eq!(
+            ResetReason::Unknown(1 << 31).to_string(),
+            "unknown (0x80000000)"
+        );
+    }
+}
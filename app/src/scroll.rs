@@ -0,0 +1,221 @@
+//! Scrolls a line of text across the matrix from right to left.
+//!
+//! The text is laid out as a strip of glyphs separated by a blank column.
+//! The strip starts just off the right edge of the display and moves one
+//! column every `column_ms` milliseconds until it has left the display
+//! entirely.
+
+use crate::display::{Image, COLS, MAX_LEVEL, ROWS};
+use crate::font;
+
+/// Glyph width plus the blank column that separates it from the next.
+const CHAR_WIDTH: usize = COLS + 1;
+
+/// Text of up to `N` bytes held by value, for scrolling text that is not
+/// around for long enough to borrow, such as text received from the host.
+#[derive(Clone, Copy, Debug)]
+pub struct Text<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Text<N> {
+    /// Copy as much of `text` as fits, dropping any characters beyond.
+    pub fn new(text: &str) -> Self {
+        let mut len = text.len().min(N);
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut bytes = [0; N];
+        bytes[..len].copy_from_slice(&text.as_bytes()[..len]);
+        Self { bytes, len }
+    }
+}
+
+impl<const N: usize> AsRef<str> for Text<N> {
+    fn as_ref(&self) -> &str {
+        // Only ever copied from a str, up to a character boundary
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Scroller<T> {
+    text: T,
+    column_ms: u32,
+    offset: usize,
+    elapsed_ms: u32,
+}
+
+impl<T: AsRef<str>> Scroller<T> {
+    /// Scroll `text`, moving one column every `column_ms` milliseconds.
+    pub fn new(text: T, column_ms: u32) -> Self {
+        Self {
+            text,
+            column_ms,
+            offset: 0,
+            elapsed_ms: 0,
+        }
+    }
+
+    /// The number of frames needed to scroll the text fully through.
+    pub fn len(&self) -> usize {
+        match self.text.as_ref().chars().count() {
+            0 => 0,
+            chars => chars * CHAR_WIDTH + COLS - 1,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.offset >= self.len()
+    }
+
+    /// Let time pass, returning the new frame if the text has moved.
+    pub fn advance(&mut self, ms: u32) -> Option<Image> {
+        self.elapsed_ms += ms;
+        let mut frame = None;
+        while self.elapsed_ms >= self.column_ms && !self.is_finished() {
+            self.elapsed_ms -= self.column_ms;
+            frame = self.next();
+        }
+        frame
+    }
+
+    /// The column of the strip at `x`, where the first `COLS` columns are
+    /// the blank lead-in to the right of the text.
+    fn column(&self, x: usize) -> [bool; ROWS] {
+        let mut column = [false; ROWS];
+        if x < COLS {
+            return column;
+        }
+        let (index, col) = ((x - COLS) / CHAR_WIDTH, (x - COLS) % CHAR_WIDTH);
+        if col < COLS {
+            if let Some(c) = self.text.as_ref().chars().nth(index) {
+                let glyph = font::glyph(c);
+                for (row, lit) in column.iter_mut().enumerate() {
+                    *lit = font::is_lit(glyph, row, col);
+                }
+            }
+        }
+        column
+    }
+}
+
+impl<T: AsRef<str>> Iterator for Scroller<T> {
+    type Item = Image;
+
+    fn next(&mut self) -> Option<Image> {
+        if self.is_finished() {
+            return None;
+        }
+        self.offset += 1;
+
+        let mut image = Image::blank();
+        for col in 0..COLS {
+            let column = self.column(self.offset + col);
+            for (row, lit) in column.iter().enumerate() {
+                if *lit {
+                    image.set_pixel(row, col, MAX_LEVEL);
+                }
+            }
+        }
+        Some(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_text_has_no_frames() {
+        let mut scroller = Scroller::new("", 100);
+        assert!(scroller.is_empty());
+        assert_eq!(scroller.next(), None);
+    }
+
+    #[test]
+    fn test_frame_count() {
+        assert_eq!(Scroller::new("I", 100).count(), 10);
+        assert_eq!(Scroller::new("Hi", 100).count(), 16);
+    }
+
+    #[test]
+    fn test_text_enters_from_the_right() {
+        let frames: Vec<Image> = Scroller::new("I", 100).collect();
+        assert_eq!(
+            frames[0],
+            Image::new([
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+            ])
+        );
+        assert_eq!(
+            frames[3],
+            Image::new([
+                [0, 0, 9, 9, 9],
+                [0, 0, 0, 9, 0],
+                [0, 0, 0, 9, 0],
+                [0, 0, 0, 9, 0],
+                [0, 0, 9, 9, 9],
+            ])
+        );
+    }
+
+    #[test]
+    fn test_glyph_is_centred_then_leaves() {
+        let frames: Vec<Image> = Scroller::new("I", 100).collect();
+        assert_eq!(frames[4], font::image('I'));
+        assert_eq!(
+            frames[6],
+            Image::new([
+                [9, 9, 0, 0, 0],
+                [9, 0, 0, 0, 0],
+                [9, 0, 0, 0, 0],
+                [9, 0, 0, 0, 0],
+                [9, 9, 0, 0, 0],
+            ])
+        );
+        assert_eq!(frames.last(), Some(&Image::blank()));
+    }
+
+    #[test]
+    fn test_advance_moves_at_the_configured_speed() {
+        let mut scroller = Scroller::new("Hi", 100);
+        assert_eq!(scroller.advance(60), None);
+        assert!(scroller.advance(60).is_some());
+        assert_eq!(scroller.advance(70), None);
+        let expected = Scroller::new("Hi", 100).nth(3);
+        assert_eq!(scroller.advance(300), expected);
+    }
+
+    #[test]
+    fn test_text_is_cut_at_a_character_boundary() {
+        assert_eq!(Text::<8>::new("Hi").as_ref(), "Hi");
+        assert_eq!(Text::<4>::new("Hello").as_ref(), "Hell");
+        assert_eq!(Text::<4>::new("Hi\u{b0}C").as_ref(), "Hi\u{b0}");
+        assert_eq!(Text::<3>::new("Hi\u{b0}C").as_ref(), "Hi");
+    }
+
+    #[test]
+    fn test_owned_text_scrolls_the_same() {
+        let borrowed: Vec<Image> = Scroller::new("Hi", 100).collect();
+        let owned: Vec<Image> = Scroller::new(Text::<8>::new("Hi"), 100).collect();
+        assert_eq!(owned, borrowed);
+    }
+
+    #[test]
+    fn test_advance_stops_when_finished() {
+        let mut scroller = Scroller::new("Hi", 10);
+        scroller.advance(10_000);
+        assert!(scroller.is_finished());
+        assert_eq!(scroller.advance(10), None);
+    }
+}
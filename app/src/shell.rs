@@ -0,0 +1,440 @@
+//! A line based command shell for a serial terminal.
+//!
+//! Bytes from the terminal are fed to a `LineEditor`, which decides what to
+//! echo back and lets the line be corrected with backspace. Each finished
+//! line is split into a command name and its arguments, and dispatched to the
+//! matching entry of a table of `Command`s. The commands are handed a context
+//! of the firmware's choosing, which is also where all output goes.
+
+use core::fmt::{self, Write};
+use core::str::{FromStr, SplitWhitespace};
+
+pub const PROMPT: &str = "> ";
+
+const CTRL_C: u8 = 0x03;
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7f;
+
+/// What the terminal should be shown in response to a byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// Nothing, as the byte is not something we edit with.
+    Ignored,
+    /// The byte was added to the line and should be echoed.
+    Echo(u8),
+    /// The last character was removed from the line and should be rubbed out.
+    Erase,
+    /// The byte could not be taken as the line is full, or there is nothing
+    /// left to erase.
+    Bell,
+    /// The line was thrown away.
+    Cancel,
+    /// Enter was pressed. The finished line is available from `line` until
+    /// the next byte is fed.
+    Line,
+}
+
+/// Collects printable ASCII into a line of up to `N` bytes.
+pub struct LineEditor<const N: usize> {
+    buffer: [u8; N],
+    len: usize,
+    after_cr: bool,
+    finished: bool,
+}
+
+impl<const N: usize> LineEditor<N> {
+    pub const fn new() -> Self {
+        Self {
+            buffer: [0; N],
+            len: 0,
+            after_cr: false,
+            finished: false,
+        }
+    }
+
+    pub fn feed(&mut self, byte: u8) -> Edit {
+        if self.finished {
+            self.finished = false;
+            self.len = 0;
+        }
+        let after_cr = core::mem::replace(&mut self.after_cr, byte == b'\r');
+
+        match byte {
+            // Terminals may send either or both of CR and LF for enter
+            b'\n' if after_cr => Edit::Ignored,
+            b'\r' | b'\n' => {
+                self.finished = true;
+                Edit::Line
+            }
+            BACKSPACE | DELETE if self.len == 0 => Edit::Bell,
+            BACKSPACE | DELETE => {
+                self.len -= 1;
+                Edit::Erase
+            }
+            CTRL_C => {
+                self.len = 0;
+                Edit::Cancel
+            }
+            b' '..=b'~' if self.len == N => Edit::Bell,
+            b' '..=b'~' => {
+                self.buffer[self.len] = byte;
+                self.len += 1;
+                Edit::Echo(byte)
+            }
+            _ => Edit::Ignored,
+        }
+    }
+
+    /// The line so far, or the finished line after `Edit::Line`.
+    pub fn line(&self) -> &str {
+        // Only printable ASCII is ever taken into the buffer
+        core::str::from_utf8(&self.buffer[..self.len]).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> Default for LineEditor<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    UnknownCommand,
+    MissingArgument,
+    InvalidArgument,
+    TooManyArguments,
+    /// Writing to the context failed.
+    Output,
+}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Self {
+        Error::Output
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Error::UnknownCommand => "unknown command",
+            Error::MissingArgument => "missing argument",
+            Error::InvalidArgument => "invalid argument",
+            Error::TooManyArguments => "too many arguments",
+            Error::Output => "output failed",
+        })
+    }
+}
+
+/// The whitespace separated arguments following a command's name.
+#[derive(Clone, Debug)]
+pub struct Args<'a>(SplitWhitespace<'a>);
+
+impl<'a> Args<'a> {
+    /// The next argument, which must be there.
+    pub fn required(&mut self) -> Result<&'a str, Error> {
+        self.0.next().ok_or(Error::MissingArgument)
+    }
+
+    /// The next argument, which must be there and parse as a `T`.
+    pub fn parse<T: FromStr>(&mut self) -> Result<T, Error> {
+        self.required()?.parse().map_err(|_| Error::InvalidArgument)
+    }
+
+    /// Check that every argument has been taken.
+    pub fn end(mut self) -> Result<(), Error> {
+        match self.0.next() {
+            Some(_) => Err(Error::TooManyArguments),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> Iterator for Args<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.0.next()
+    }
+}
+
+/// Split a line into its command name and arguments, or `None` if the line
+/// is blank.
+pub fn parse(line: &str) -> Option<(&str, Args<'_>)> {
+    let mut words = line.split_whitespace();
+    let name = words.next()?;
+    Some((name, Args(words)))
+}
+
+pub struct Command<C> {
+    pub name: &'static str,
+    /// The arguments taken, shown by `help` and when they are wrong.
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub run: fn(&mut C, Args<'_>) -> Result<(), Error>,
+}
+
+/// Runs the commands typed into a line editor, with a built in `help`
+/// command listing the rest.
+pub struct Shell<C: 'static, const N: usize> {
+    editor: LineEditor<N>,
+    commands: &'static [Command<C>],
+}
+
+impl<C: Write, const N: usize> Shell<C, N> {
+    pub const fn new(commands: &'static [Command<C>]) -> Self {
+        Self {
+            editor: LineEditor::new(),
+            commands,
+        }
+    }
+
+    pub fn prompt(&self, context: &mut C) -> fmt::Result {
+        context.write_str(PROMPT)
+    }
+
+    /// Take a byte from the terminal, echoing it to the context and running
+    /// the line once it is finished.
+    pub fn feed(&mut self, context: &mut C, byte: u8) -> fmt::Result {
+        match self.editor.feed(byte) {
+            Edit::Ignored => Ok(()),
+            Edit::Echo(byte) => context.write_char(char::from(byte)),
+            Edit::Erase => context.write_str("\x08 \x08"),
+            Edit::Bell => context.write_char('\x07'),
+            Edit::Cancel => {
+                context.write_str("^C\r\n")?;
+                self.prompt(context)
+            }
+            Edit::Line => {
+                context.write_str("\r\n")?;
+                self.execute(context, self.editor.line())?;
+                self.prompt(context)
+            }
+        }
+    }
+
+    /// Run a line, reporting any error to the context.
+    pub fn execute(&self, context: &mut C, line: &str) -> fmt::Result {
+        let (name, args) = match parse(line) {
+            Some(parsed) => parsed,
+            None => return Ok(()),
+        };
+        if name == "help" {
+            return self.help(context);
+        }
+        let command = match self.commands.iter().find(|command| command.name == name) {
+            Some(command) => command,
+            None => return write!(context, "{}: {}, try help\r\n", name, Error::UnknownCommand),
+        };
+        match (command.run)(context, args) {
+            Ok(()) => Ok(()),
+            Err(Error::Output) => Err(fmt::Error),
+            Err(error) => write!(
+                context,
+                "{}: {}\r\nusage: {} {}\r\n",
+                name, error, name, command.usage
+            ),
+        }
+    }
+
+    fn help(&self, context: &mut C) -> fmt::Result {
+        context.write_str("help\r\n    List the commands\r\n")?;
+        for command in self.commands {
+            context.write_str(command.name)?;
+            if !command.usage.is_empty() {
+                write!(context, " {}", command.usage)?;
+            }
+            write!(context, "\r\n    {}\r\n", command.help)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_into<const N: usize>(editor: &mut LineEditor<N>, bytes: &[u8]) -> Vec<Edit> {
+        bytes.iter().map(|byte| editor.feed(*byte)).collect()
+    }
+
+    #[test]
+    fn test_editor_echoes_and_finishes_lines() {
+        let mut editor: LineEditor<16> = LineEditor::new();
+        assert_eq!(
+            type_into(&mut editor, b"hi\r"),
+            vec![Edit::Echo(b'h'), Edit::Echo(b'i'), Edit::Line]
+        );
+        assert_eq!(editor.line(), "hi");
+        assert_eq!(editor.feed(b'x'), Edit::Echo(b'x'));
+        assert_eq!(editor.line(), "x");
+    }
+
+    #[test]
+    fn test_editor_takes_crlf_as_one_enter() {
+        let mut editor: LineEditor<16> = LineEditor::new();
+        assert_eq!(
+            type_into(&mut editor, b"a\r\n\n"),
+            vec![Edit::Echo(b'a'), Edit::Line, Edit::Ignored, Edit::Line]
+        );
+        assert_eq!(editor.line(), "");
+    }
+
+    #[test]
+    fn test_editor_backspace() {
+        let mut editor: LineEditor<16> = LineEditor::new();
+        assert_eq!(
+            type_into(&mut editor, b"ab\x08c\x7f\x7f\x08"),
+            vec![
+                Edit::Echo(b'a'),
+                Edit::Echo(b'b'),
+                Edit::Erase,
+                Edit::Echo(b'c'),
+                Edit::Erase,
+                Edit::Erase,
+                Edit::Bell
+            ]
+        );
+        assert_eq!(editor.line(), "");
+    }
+
+    #[test]
+    fn test_editor_full_line_rings_the_bell() {
+        let mut editor: LineEditor<3> = LineEditor::new();
+        assert_eq!(
+            type_into(&mut editor, b"abcd"),
+            vec![
+                Edit::Echo(b'a'),
+                Edit::Echo(b'b'),
+                Edit::Echo(b'c'),
+                Edit::Bell
+            ]
+        );
+        assert_eq!(editor.line(), "abc");
+    }
+
+    #[test]
+    fn test_editor_cancel_and_ignored_bytes() {
+        let mut editor: LineEditor<16> = LineEditor::new();
+        assert_eq!(
+            type_into(&mut editor, b"ab\x03\x1b\xffc"),
+            vec![
+                Edit::Echo(b'a'),
+                Edit::Echo(b'b'),
+                Edit::Cancel,
+                Edit::Ignored,
+                Edit::Ignored,
+                Edit::Echo(b'c')
+            ]
+        );
+        assert_eq!(editor.line(), "c");
+    }
+
+    #[test]
+    fn test_parse_splits_on_whitespace() {
+        let (name, args) = parse("  led  fill\t5 ").unwrap();
+        assert_eq!(name, "led");
+        assert_eq!(args.collect::<Vec<_>>(), vec!["fill", "5"]);
+        assert!(parse(" \t ").is_none());
+    }
+
+    #[test]
+    fn test_args() {
+        let (_, mut args) = parse("set 12 x").unwrap();
+        assert_eq!(args.parse::<u8>(), Ok(12));
+        assert_eq!(args.clone().parse::<u8>(), Err(Error::InvalidArgument));
+        assert_eq!(args.clone().end(), Err(Error::TooManyArguments));
+        assert_eq!(args.required(), Ok("x"));
+        assert_eq!(args.clone().required(), Err(Error::MissingArgument));
+        assert_eq!(args.end(), Ok(()));
+    }
+
+    #[derive(Default)]
+    struct Context {
+        output: String,
+        total: u32,
+    }
+
+    impl Write for Context {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.output.push_str(s);
+            Ok(())
+        }
+    }
+
+    static COMMANDS: [Command<Context>; 2] = [
+        Command {
+            name: "add",
+            usage: "<n>",
+            help: "Add to the total",
+            run: |context, mut args| {
+                let n = args.parse::<u32>()?;
+                args.end()?;
+                context.total += n;
+                Ok(())
+            },
+        },
+        Command {
+            name: "total",
+            usage: "",
+            help: "Show the total",
+            run: |context, args| {
+                args.end()?;
+                let total = context.total;
+                write!(context, "{}\r\n", total)?;
+                Ok(())
+            },
+        },
+    ];
+
+    fn session(input: &[u8]) -> Context {
+        let mut shell: Shell<Context, 16> = Shell::new(&COMMANDS);
+        let mut context = Context::default();
+        for byte in input {
+            shell.feed(&mut context, *byte).unwrap();
+        }
+        context
+    }
+
+    #[test]
+    fn test_shell_runs_commands() {
+        let context = session(b"add 2\radd 3\rtotal\r");
+        assert_eq!(context.total, 5);
+        assert_eq!(context.output, "add 2\r\n> add 3\r\n> total\r\n5\r\n> ");
+    }
+
+    #[test]
+    fn test_shell_edits_before_running() {
+        let context = session(b"add 9\x087\r");
+        assert_eq!(context.total, 7);
+        assert_eq!(context.output, "add 9\x08 \x087\r\n> ");
+    }
+
+    #[test]
+    fn test_shell_reports_errors() {
+        let context = session(b"sub 1\radd\radd 1 2\r");
+        assert_eq!(context.total, 0);
+        assert_eq!(
+            context.output,
+            "sub 1\r\nsub: unknown command, try help\r\n\
+             > add\r\nadd: missing argument\r\nusage: add <n>\r\n\
+             > add 1 2\r\nadd: too many arguments\r\nusage: add <n>\r\n> "
+        );
+    }
+
+    #[test]
+    fn test_shell_help_lists_commands() {
+        let context = session(b"help\r");
+        assert_eq!(
+            context.output,
+            "help\r\nhelp\r\n    List the commands\r\n\
+             add <n>\r\n    Add to the total\r\n\
+             total\r\n    Show the total\r\n> "
+        );
+    }
+
+    #[test]
+    fn test_shell_blank_line_just_prompts() {
+        assert_eq!(session(b"  \r").output, "  \r\n> ");
+    }
+}
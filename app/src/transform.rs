@@ -0,0 +1,299 @@
+//! Geometric and level transforms of matrix images.
+
+use crate::display::{Image, COLS, MAX_LEVEL, ROWS};
+
+/// A clockwise rotation in steps of 90 degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Image {
+    /// Rotate clockwise. The matrix is square so nothing is lost.
+    pub fn rotated(&self, rotation: Rotation) -> Image {
+        let src = self.pixels();
+        let mut pixels = [[0; COLS]; ROWS];
+        for (row, line) in pixels.iter_mut().enumerate() {
+            for (col, pixel) in line.iter_mut().enumerate() {
+                *pixel = match rotation {
+                    Rotation::None => src[row][col],
+                    Rotation::Cw90 => src[ROWS - 1 - col][row],
+                    Rotation::Cw180 => src[ROWS - 1 - row][COLS - 1 - col],
+                    Rotation::Cw270 => src[col][COLS - 1 - row],
+                };
+            }
+        }
+        Image::new(pixels)
+    }
+
+    /// Mirror left to right.
+    pub fn flipped_horizontally(&self) -> Image {
+        let mut pixels = *self.pixels();
+        for line in pixels.iter_mut() {
+            line.reverse();
+        }
+        Image::new(pixels)
+    }
+
+    /// Mirror top to bottom.
+    pub fn flipped_vertically(&self) -> Image {
+        let mut pixels = *self.pixels();
+        pixels.reverse();
+        Image::new(pixels)
+    }
+
+    /// Swap bright for dark, so that each level becomes `MAX_LEVEL - level`.
+    pub fn inverted(&self) -> Image {
+        let mut pixels = *self.pixels();
+        for pixel in pixels.iter_mut().flat_map(|line| line.iter_mut()) {
+            *pixel = MAX_LEVEL - (*pixel).min(MAX_LEVEL);
+        }
+        Image::new(pixels)
+    }
+
+    /// Move the image right by `dx` and down by `dy` columns and rows, where
+    /// negative values move it left and up. Pixels moved off one edge either
+    /// come back on the opposite edge when `wrap` is set, or are lost and
+    /// replaced with blank pixels.
+    pub fn shifted(&self, dx: i32, dy: i32, wrap: bool) -> Image {
+        let src = self.pixels();
+        let mut pixels = [[0; COLS]; ROWS];
+        for (row, line) in pixels.iter_mut().enumerate() {
+            for (col, pixel) in line.iter_mut().enumerate() {
+                // Wide enough that no shift can overflow
+                let src_row = row as i64 - i64::from(dy);
+                let src_col = col as i64 - i64::from(dx);
+                *pixel = if wrap {
+                    src[src_row.rem_euclid(ROWS as i64) as usize]
+                        [src_col.rem_euclid(COLS as i64) as usize]
+                } else if (0..ROWS as i64).contains(&src_row) && (0..COLS as i64).contains(&src_col)
+                {
+                    src[src_row as usize][src_col as usize]
+                } else {
+                    0
+                };
+            }
+        }
+        Image::new(pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROTATIONS: [Rotation; 4] = [
+        Rotation::None,
+        Rotation::Cw90,
+        Rotation::Cw180,
+        Rotation::Cw270,
+    ];
+
+    /// Every pixel holds a distinct value so that any misplacement shows.
+    fn numbered() -> Image {
+        let mut pixels = [[0; COLS]; ROWS];
+        for (row, line) in pixels.iter_mut().enumerate() {
+            for (col, pixel) in line.iter_mut().enumerate() {
+                *pixel = (row * COLS + col) as u8;
+            }
+        }
+        Image::new(pixels)
+    }
+
+    fn position_of(image: &Image, value: u8) -> (usize, usize) {
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                if image.pixel(row, col) == value {
+                    return (row, col);
+                }
+            }
+        }
+        panic!("{} is missing", value);
+    }
+
+    #[test]
+    fn test_rotation_moves_every_pixel() {
+        let image = numbered();
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                let value = image.pixel(row, col);
+                assert_eq!(
+                    position_of(&image.rotated(Rotation::None), value),
+                    (row, col)
+                );
+                assert_eq!(
+                    position_of(&image.rotated(Rotation::Cw90), value),
+                    (col, ROWS - 1 - row)
+                );
+                assert_eq!(
+                    position_of(&image.rotated(Rotation::Cw180), value),
+                    (ROWS - 1 - row, COLS - 1 - col)
+                );
+                assert_eq!(
+                    position_of(&image.rotated(Rotation::Cw270), value),
+                    (COLS - 1 - col, row)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotations_compose() {
+        let image = numbered();
+        let quarter = |image: Image, times: usize| {
+            (0..times).fold(image, |image, _| image.rotated(Rotation::Cw90))
+        };
+        for (times, rotation) in ROTATIONS.iter().enumerate() {
+            assert_eq!(image.rotated(*rotation), quarter(image, times));
+        }
+        assert_eq!(quarter(image, 4), image);
+    }
+
+    #[test]
+    fn test_rotate_top_row_clockwise() {
+        let image = Image::new([
+            [9, 9, 9, 9, 9],
+            [0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0],
+        ]);
+        assert_eq!(
+            image.rotated(Rotation::Cw90),
+            Image::new([
+                [0, 0, 0, 0, 9],
+                [0, 0, 0, 0, 9],
+                [0, 0, 0, 0, 9],
+                [0, 0, 0, 0, 9],
+                [0, 0, 0, 0, 9],
+            ])
+        );
+    }
+
+    #[test]
+    fn test_flips_mirror_every_pixel() {
+        let image = numbered();
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                let value = image.pixel(row, col);
+                assert_eq!(
+                    position_of(&image.flipped_horizontally(), value),
+                    (row, COLS - 1 - col)
+                );
+                assert_eq!(
+                    position_of(&image.flipped_vertically(), value),
+                    (ROWS - 1 - row, col)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_flips_are_their_own_inverse() {
+        let image = numbered();
+        assert_eq!(image.flipped_horizontally().flipped_horizontally(), image);
+        assert_eq!(image.flipped_vertically().flipped_vertically(), image);
+        assert_eq!(
+            image.flipped_horizontally().flipped_vertically(),
+            image.rotated(Rotation::Cw180)
+        );
+    }
+
+    #[test]
+    fn test_invert_every_level() {
+        for level in 0..=MAX_LEVEL {
+            let image = Image::new([[level; COLS]; ROWS]);
+            assert_eq!(
+                image.inverted(),
+                Image::new([[MAX_LEVEL - level; COLS]; ROWS])
+            );
+            assert_eq!(image.inverted().inverted(), image);
+        }
+    }
+
+    #[test]
+    fn test_shift_with_wrap_loses_nothing() {
+        let image = numbered();
+        for dy in -(ROWS as i32)..=ROWS as i32 {
+            for dx in -(COLS as i32)..=COLS as i32 {
+                let shifted = image.shifted(dx, dy, true);
+                for row in 0..ROWS {
+                    for col in 0..COLS {
+                        let expected = (
+                            (row as i32 + dy).rem_euclid(ROWS as i32) as usize,
+                            (col as i32 + dx).rem_euclid(COLS as i32) as usize,
+                        );
+                        assert_eq!(position_of(&shifted, image.pixel(row, col)), expected);
+                    }
+                }
+                assert_eq!(shifted.shifted(-dx, -dy, true), image);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shift_without_wrap_blanks_the_edges() {
+        let image = Image::new([[MAX_LEVEL; COLS]; ROWS]);
+        for dy in -(ROWS as i32)..=ROWS as i32 {
+            for dx in -(COLS as i32)..=COLS as i32 {
+                let shifted = image.shifted(dx, dy, false);
+                for row in 0..ROWS as i32 {
+                    for col in 0..COLS as i32 {
+                        let kept = (0..ROWS as i32).contains(&(row - dy))
+                            && (0..COLS as i32).contains(&(col - dx));
+                        let expected = if kept { MAX_LEVEL } else { 0 };
+                        assert_eq!(shifted.pixel(row as usize, col as usize), expected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_extreme_shifts() {
+        let image = numbered();
+        for shift in [i32::MIN, i32::MIN + 1, i32::MAX].iter() {
+            let (dx, dy) = (*shift, *shift);
+            let wrapped =
+                image.shifted(dx.rem_euclid(COLS as i32), dy.rem_euclid(ROWS as i32), true);
+            assert_eq!(image.shifted(dx, dy, true), wrapped);
+            assert_eq!(image.shifted(dx, 0, false), Image::blank());
+            assert_eq!(image.shifted(0, dy, false), Image::blank());
+        }
+    }
+
+    #[test]
+    fn test_shift_right_by_one() {
+        let image = Image::new([
+            [1, 0, 0, 0, 2],
+            [0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0],
+        ]);
+        assert_eq!(
+            image.shifted(1, 0, false),
+            Image::new([
+                [0, 1, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+            ])
+        );
+        assert_eq!(
+            image.shifted(1, 0, true),
+            Image::new([
+                [2, 1, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+            ])
+        );
+    }
+}
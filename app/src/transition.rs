@@ -0,0 +1,215 @@
+//! Transitions from one matrix image to another over a number of steps.
+
+use crate::display::{Image, COLS, ROWS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Effect {
+    /// Blend the levels of every pixel from one image to the other.
+    CrossFade,
+    /// Sweep an edge across the display in the given direction, with the
+    /// new image behind it.
+    Wipe(Direction),
+    /// Switch pixels over one at a time in a fixed, scattered order.
+    Dissolve,
+}
+
+/// The order in which pixels switch over when dissolving, as indices of
+/// `row * COLS + col`. A fixed shuffle, so that the effect looks random but
+/// is the same every time.
+const DISSOLVE_ORDER: [u8; ROWS * COLS] = [
+    2, 10, 4, 12, 9, 13, 24, 22, 18, 23, 6, 3, 17, 15, 21, 5, 1, 7, 14, 0, 16, 20, 11, 8, 19,
+];
+
+/// Yields `steps` frames, the last of which is the `to` image.
+#[derive(Clone, Debug)]
+pub struct Transition {
+    from: Image,
+    to: Image,
+    effect: Effect,
+    steps: usize,
+    step: usize,
+}
+
+impl Transition {
+    pub fn new(from: Image, to: Image, effect: Effect, steps: usize) -> Self {
+        Self {
+            from,
+            to,
+            effect,
+            steps,
+            step: 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.step >= self.steps
+    }
+
+    /// The frame `step` steps of the way through, where step 0 is the `from`
+    /// image and `steps` is the `to` image.
+    pub fn frame(&self, step: usize) -> Image {
+        if self.steps == 0 || step >= self.steps {
+            return self.to;
+        }
+        let (n, k) = (self.steps, step);
+        let mut image = self.from;
+        match self.effect {
+            Effect::CrossFade => {
+                for row in 0..ROWS {
+                    for col in 0..COLS {
+                        let from = usize::from(self.from.pixel(row, col));
+                        let to = usize::from(self.to.pixel(row, col));
+                        let level = (from * (n - k) + to * k + n / 2) / n;
+                        image.set_pixel(row, col, level as u8);
+                    }
+                }
+            }
+            Effect::Wipe(direction) => {
+                let (lines, count) = match direction {
+                    Direction::Left | Direction::Right => (COLS, COLS * k / n),
+                    Direction::Up | Direction::Down => (ROWS, ROWS * k / n),
+                };
+                for row in 0..ROWS {
+                    for col in 0..COLS {
+                        let covered = match direction {
+                            Direction::Left => col >= lines - count,
+                            Direction::Right => col < count,
+                            Direction::Up => row >= lines - count,
+                            Direction::Down => row < count,
+                        };
+                        if covered {
+                            image.set_pixel(row, col, self.to.pixel(row, col));
+                        }
+                    }
+                }
+            }
+            Effect::Dissolve => {
+                let count = DISSOLVE_ORDER.len() * k / n;
+                for index in DISSOLVE_ORDER.iter().take(count) {
+                    let (row, col) = (usize::from(*index) / COLS, usize::from(*index) % COLS);
+                    image.set_pixel(row, col, self.to.pixel(row, col));
+                }
+            }
+        }
+        image
+    }
+}
+
+impl Iterator for Transition {
+    type Item = Image;
+
+    fn next(&mut self) -> Option<Image> {
+        if self.is_finished() {
+            return None;
+        }
+        self.step += 1;
+        Some(self.frame(self.step))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::MAX_LEVEL;
+
+    const DARK: Image = Image::new([[0; COLS]; ROWS]);
+    const LIGHT: Image = Image::new([[MAX_LEVEL; COLS]; ROWS]);
+
+    fn lit(image: &Image) -> usize {
+        image
+            .pixels()
+            .iter()
+            .flat_map(|line| line.iter())
+            .filter(|pixel| **pixel != 0)
+            .count()
+    }
+
+    #[test]
+    fn test_yields_steps_frames_ending_on_the_target() {
+        for effect in [
+            Effect::CrossFade,
+            Effect::Wipe(Direction::Left),
+            Effect::Wipe(Direction::Down),
+            Effect::Dissolve,
+        ]
+        .iter()
+        {
+            let frames: Vec<Image> = Transition::new(DARK, LIGHT, *effect, 7).collect();
+            assert_eq!(frames.len(), 7);
+            assert_eq!(frames.last(), Some(&LIGHT));
+        }
+    }
+
+    #[test]
+    fn test_zero_steps_goes_straight_to_the_target() {
+        let transition = Transition::new(DARK, LIGHT, Effect::CrossFade, 0);
+        assert!(transition.is_finished());
+        assert_eq!(transition.frame(0), LIGHT);
+    }
+
+    #[test]
+    fn test_cross_fade_blends_levels() {
+        let transition = Transition::new(DARK, LIGHT, Effect::CrossFade, 3);
+        assert_eq!(transition.frame(0), DARK);
+        assert_eq!(transition.frame(1), Image::new([[3; COLS]; ROWS]));
+        assert_eq!(transition.frame(2), Image::new([[6; COLS]; ROWS]));
+        let levels: Vec<u8> = Transition::new(LIGHT, DARK, Effect::CrossFade, 9)
+            .map(|image| image.pixel(0, 0))
+            .collect();
+        assert_eq!(levels, vec![8, 7, 6, 5, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_horizontal_wipes() {
+        let transition = Transition::new(DARK, LIGHT, Effect::Wipe(Direction::Left), 5);
+        assert_eq!(transition.frame(2), Image::new([[0, 0, 0, 9, 9]; ROWS]));
+        let transition = Transition::new(DARK, LIGHT, Effect::Wipe(Direction::Right), 5);
+        assert_eq!(transition.frame(2), Image::new([[9, 9, 0, 0, 0]; ROWS]));
+    }
+
+    #[test]
+    fn test_vertical_wipes() {
+        let transition = Transition::new(DARK, LIGHT, Effect::Wipe(Direction::Up), 5);
+        assert_eq!(
+            transition.frame(1),
+            Image::new([[0; COLS], [0; COLS], [0; COLS], [0; COLS], [9; COLS]])
+        );
+        let transition = Transition::new(DARK, LIGHT, Effect::Wipe(Direction::Down), 5);
+        assert_eq!(
+            transition.frame(1),
+            Image::new([[9; COLS], [0; COLS], [0; COLS], [0; COLS], [0; COLS]])
+        );
+    }
+
+    #[test]
+    fn test_dissolve_switches_pixels_progressively() {
+        let counts: Vec<usize> = Transition::new(DARK, LIGHT, Effect::Dissolve, 5)
+            .map(|image| lit(&image))
+            .collect();
+        assert_eq!(counts, vec![5, 10, 15, 20, 25]);
+    }
+
+    #[test]
+    fn test_dissolve_order_is_a_permutation() {
+        let mut seen = [false; ROWS * COLS];
+        for index in DISSOLVE_ORDER.iter() {
+            assert!(!seen[usize::from(*index)]);
+            seen[usize::from(*index)] = true;
+        }
+    }
+
+    #[test]
+    fn test_dissolve_is_repeatable() {
+        let a: Vec<Image> = Transition::new(DARK, LIGHT, Effect::Dissolve, 25).collect();
+        let b: Vec<Image> = Transition::new(DARK, LIGHT, Effect::Dissolve, 25).collect();
+        assert_eq!(a, b);
+    }
+}
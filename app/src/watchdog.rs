@@ -0,0 +1,286 @@
+//! Supervises tasks that must each check in within a deadline, so that a
+//! hardware watchdog need only be fed while all of them are keeping up.
+//!
+//! A task that falls behind can be kept in a record that outlives the reset
+//! the watchdog then brings, as `crash` does for panics.
+
+use crate::crash::fnv1a;
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// A task to supervise, checking in by its index in the supervisor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Task {
+    pub name: &'static str,
+    /// The longest that may pass between check-ins.
+    pub deadline_ms: u32,
+}
+
+/// A task that went too long without checking in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Missed<'a> {
+    pub name: &'a str,
+    /// How long past its deadline it was.
+    pub late_ms: u32,
+}
+
+impl<'a> fmt::Display for Missed<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} missed its deadline by {} ms",
+            self.name, self.late_ms
+        )
+    }
+}
+
+/// Check-ins are kept in atomics, so that tasks can check in and the
+/// watchdog's interrupt can check on them without a critical section. Each
+/// task is the only writer of its own time, so only loads and stores are
+/// needed, which the v1's Cortex-M0 has.
+pub struct Supervisor<const N: usize> {
+    tasks: [Task; N],
+    checked_in_ms: [AtomicU32; N],
+}
+
+impl<const N: usize> Supervisor<N> {
+    pub const fn new(tasks: [Task; N]) -> Self {
+        Self {
+            tasks,
+            checked_in_ms: [const { AtomicU32::new(0) }; N],
+        }
+    }
+
+    /// Count every task as having just checked in, such as once ready to
+    /// start feeding the watchdog.
+    pub fn start(&self, now_ms: u32) {
+        for checked_in_ms in self.checked_in_ms.iter() {
+            checked_in_ms.store(now_ms, Ordering::Relaxed);
+        }
+    }
+
+    /// Note that the task at `index` is keeping up.
+    pub fn check_in(&self, index: usize, now_ms: u32) {
+        if let Some(checked_in_ms) = self.checked_in_ms.get(index) {
+            checked_in_ms.store(now_ms, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether every task has checked in within its deadline, or else the
+    /// one that is furthest behind.
+    pub fn check(&self, now_ms: u32) -> Result<(), Missed<'static>> {
+        self.tasks
+            .iter()
+            .zip(self.checked_in_ms.iter())
+            .filter_map(|(task, checked_in_ms)| {
+                let late_ms = now_ms
+                    .wrapping_sub(checked_in_ms.load(Ordering::Relaxed))
+                    .checked_sub(task.deadline_ms)
+                    .filter(|late_ms| *late_ms > 0)?;
+                Some(Missed {
+                    name: task.name,
+                    late_ms,
+                })
+            })
+            .max_by_key(|missed| missed.late_ms)
+            .map_or(Ok(()), Err)
+    }
+}
+
+/// The most of a task's name that is kept.
+pub const NAME_LEN: usize = 16;
+
+/// Marks a record as written by `MissedRecord::record`.
+pub const MAGIC: u32 = 0x5744_4f47;
+
+/// A missed deadline kept in RAM across a reset, trusted only if its magic
+/// number and checksum agree.
+#[repr(C)]
+pub struct MissedRecord {
+    magic: u32,
+    late_ms: u32,
+    name_len: u32,
+    name: [u8; NAME_LEN],
+    checksum: u32,
+}
+
+impl MissedRecord {
+    /// A record that holds no missed deadline.
+    pub const fn new() -> Self {
+        Self {
+            magic: 0,
+            late_ms: 0,
+            name_len: 0,
+            name: [0; NAME_LEN],
+            checksum: 0,
+        }
+    }
+
+    /// Keep a missed deadline, cutting the task's name short if it won't
+    /// fit.
+    pub fn record(&mut self, missed: &Missed) {
+        let mut len = missed.name.len().min(NAME_LEN);
+        while !missed.name.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.name[..len].copy_from_slice(&missed.name.as_bytes()[..len]);
+        self.name_len = len as u32;
+        self.late_ms = missed.late_ms;
+        self.magic = MAGIC;
+        self.checksum = self.checksum();
+    }
+
+    /// The missed deadline recorded, if the record can be trusted.
+    pub fn missed(&self) -> Option<Missed<'_>> {
+        if self.magic != MAGIC || self.checksum != self.checksum() {
+            return None;
+        }
+        let name = self.name.get(..self.name_len as usize)?;
+        Some(Missed {
+            name: core::str::from_utf8(name).ok()?,
+            late_ms: self.late_ms,
+        })
+    }
+
+    /// Forget the missed deadline, so it is only reported once.
+    pub fn clear(&mut self) {
+        self.magic = 0;
+    }
+
+    /// Covers every field but the checksum itself.
+    fn checksum(&self) -> u32 {
+        let fields = [self.magic, self.late_ms, self.name_len];
+        fnv1a(
+            fields
+                .iter()
+                .flat_map(|field| field.to_le_bytes())
+                .chain(self.name.iter().copied()),
+        )
+    }
+}
+
+impl Default for MissedRecord {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supervisor() -> Supervisor<2> {
+        Supervisor::new([
+            Task {
+                name: "main loop",
+                deadline_ms: 500,
+            },
+            Task {
+                name: "display",
+                deadline_ms: 100,
+            },
+        ])
+    }
+
+    #[test]
+    fn test_healthy_while_every_task_checks_in() {
+        let supervisor = supervisor();
+        supervisor.start(1_000);
+        assert_eq!(supervisor.check(1_100), Ok(()));
+        supervisor.check_in(1, 1_100);
+        assert_eq!(supervisor.check(1_200), Ok(()));
+        supervisor.check_in(0, 1_500);
+        supervisor.check_in(1, 1_500);
+        assert_eq!(supervisor.check(1_600), Ok(()));
+    }
+
+    #[test]
+    fn test_the_task_furthest_behind_is_reported() {
+        let supervisor = supervisor();
+        supervisor.start(1_000);
+        assert_eq!(
+            supervisor.check(1_101),
+            Err(Missed {
+                name: "display",
+                late_ms: 1
+            })
+        );
+        supervisor.check_in(1, 1_420);
+        assert_eq!(supervisor.check(1_450), Ok(()));
+        assert_eq!(
+            supervisor.check(1_550),
+            Err(Missed {
+                name: "main loop",
+                late_ms: 50
+            })
+        );
+        supervisor.check_in(0, 1_550);
+        assert_eq!(
+            supervisor.check(1_600),
+            Err(Missed {
+                name: "display",
+                late_ms: 80
+            })
+        );
+    }
+
+    #[test]
+    fn test_check_ins_across_the_clock_wrapping() {
+        let supervisor = supervisor();
+        supervisor.start(u32::MAX - 50);
+        supervisor.check_in(1, u32::MAX - 10);
+        assert_eq!(supervisor.check(40), Ok(()));
+        assert_eq!(
+            supervisor.check(100),
+            Err(Missed {
+                name: "display",
+                late_ms: 11
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_tasks_are_ignored() {
+        let supervisor = supervisor();
+        supervisor.start(0);
+        supervisor.check_in(2, 100);
+        assert!(supervisor.check(101).is_err());
+    }
+
+    #[test]
+    fn test_record_and_read_back() {
+        let mut record = MissedRecord::new();
+        assert_eq!(record.missed(), None);
+
+        let missed = Missed {
+            name: "display",
+            late_ms: 12,
+        };
+        record.record(&missed);
+        assert_eq!(record.missed(), Some(missed));
+        assert_eq!(missed.to_string(), "display missed its deadline by 12 ms");
+
+        record.late_ms += 1;
+        assert_eq!(record.missed(), None);
+        record.late_ms -= 1;
+
+        record.clear();
+        assert_eq!(record.missed(), None);
+    }
+
+    #[test]
+    fn test_long_names_are_cut_at_a_character() {
+        let mut record = MissedRecord::new();
+        record.record(&Missed {
+            name: "a rather long task name",
+            late_ms: 1,
+        });
+        assert_eq!(record.missed().unwrap().name, "a rather long ta");
+
+        record.record(&Missed {
+            name: "ééééééééé",
+            late_ms: 1,
+        });
+        assert_eq!(record.missed().unwrap().name, "éééééééé");
+    }
+}
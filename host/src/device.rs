@@ -0,0 +1,423 @@
+//! A board at the other end of a serial port, driven through its shell and
+//! frames.
+//!
+//! The port is anything that reads and writes bytes. Reads are expected to
+//! give up after a while with nothing to return, as a serial port set up
+//! with a read timeout does, which is reported as `ErrorKind::TimedOut`.
+
+use app::{
+    display::Image,
+    frame::{self, Demux, Received, MAX_FRAME_LEN},
+    message::{Message, Request, Response, MAX_MESSAGE_LEN},
+    shell::PROMPT,
+};
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+
+const CTRL_C: u8 = 0x03;
+
+/// The board reports button events on lines of their own, starting with
+/// this, once asked to with `button watch on`.
+const BUTTON_EVENT: &str = "button: ";
+
+pub struct Device<P> {
+    port: P,
+    demux: Demux,
+    text: String,
+    frames: VecDeque<Vec<u8>>,
+    seq: u16,
+}
+
+impl<P: Read + Write> Device<P> {
+    pub fn new(port: P) -> Self {
+        Self {
+            port,
+            demux: Demux::new(),
+            text: String::new(),
+            frames: VecDeque::new(),
+            seq: 0,
+        }
+    }
+
+    pub fn port(&self) -> &P {
+        &self.port
+    }
+
+    /// Throw away anything half typed at the shell, and anything waiting
+    /// to be read, leaving the shell ready for a command.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.port.write_all(&[CTRL_C])?;
+        let cancelled = format!("^C\r\n{}", PROMPT);
+        while !self.text.contains(&cancelled) {
+            self.receive()?;
+        }
+        self.text.clear();
+        self.frames.clear();
+        Ok(())
+    }
+
+    /// Run a shell command, returning its output.
+    pub fn command(&mut self, line: &str) -> io::Result<String> {
+        if line.bytes().any(|byte| !(b' '..=b'~').contains(&byte)) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "commands must be printable ASCII",
+            ));
+        }
+        self.text.clear();
+        self.port.write_all(line.as_bytes())?;
+        self.port.write_all(b"\r")?;
+
+        // Whatever follows the prompt is kept, as that may be button events
+        let finished = format!("\r\n{}", PROMPT);
+        let end = loop {
+            match self.text.find(&finished) {
+                Some(at) => break at + finished.len(),
+                None => self.receive()?,
+            }
+        };
+        let mut output: String = self.text.drain(..end).collect();
+        output.truncate(output.len() - PROMPT.len());
+        // The first line is the command echoed back
+        Ok(match output.find("\r\n") {
+            Some(end) => output[end + 2..].to_string(),
+            None => output,
+        })
+    }
+
+    pub fn set_image(&mut self, image: &Image) -> io::Result<String> {
+        self.command(&format!("led image {}", image))
+    }
+
+    /// Send a frame, returning the next frame to come back.
+    pub fn exchange(&mut self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let mut buffer = [0; MAX_FRAME_LEN];
+        let len = frame::encode(payload, &mut buffer)
+            .map_err(|error| io::Error::new(ErrorKind::InvalidInput, format!("{:?}", error)))?;
+        self.frames.clear();
+        self.port.write_all(&buffer[..len])?;
+        self.next_frame()
+    }
+
+    fn next_frame(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(frame) = self.frames.pop_front() {
+                return Ok(frame);
+            }
+            self.receive()?;
+        }
+    }
+
+    /// Make a request of the board, returning its response.
+    pub fn request(&mut self, request: Request) -> io::Result<Response> {
+        self.seq = self.seq.wrapping_add(1);
+        let message = Message {
+            seq: self.seq,
+            body: request,
+        };
+        let mut buffer = [0; MAX_MESSAGE_LEN];
+        let len = message
+            .encode(&mut buffer)
+            .map_err(|error| io::Error::new(ErrorKind::InvalidInput, format!("{:?}", error)))?;
+        let mut reply = self.exchange(&buffer[..len])?;
+        loop {
+            let response = Message::<Response>::decode(&reply)
+                .map_err(|error| io::Error::new(ErrorKind::InvalidData, format!("{:?}", error)))?;
+            // Answers to requests that were given up on are passed over
+            if response.seq == self.seq {
+                return Ok(response.body);
+            }
+            reply = self.next_frame()?;
+        }
+    }
+
+    /// Ask the board to report button events, passing each one on until
+    /// `on_event` returns false. Quiet spells are waited out.
+    pub fn watch_buttons(&mut self, mut on_event: impl FnMut(&str) -> bool) -> io::Result<()> {
+        self.command("button watch on")?;
+        loop {
+            while let Some(end) = self.text.find("\r\n") {
+                let line: String = self.text.drain(..end + 2).collect();
+                if let Some(event) = line.trim_end().strip_prefix(BUTTON_EVENT) {
+                    if !on_event(event) {
+                        self.command("button watch off")?;
+                        return Ok(());
+                    }
+                }
+            }
+            match self.receive() {
+                Err(error) if error.kind() == ErrorKind::TimedOut => (),
+                result => result?,
+            }
+        }
+    }
+
+    /// Take in whatever the board has sent.
+    fn receive(&mut self) -> io::Result<()> {
+        let mut buffer = [0; 256];
+        let len = self.port.read(&mut buffer)?;
+        if len == 0 {
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                "no reply from the board",
+            ));
+        }
+        for byte in &buffer[..len] {
+            match self.demux.feed(*byte) {
+                Some(Received::Text(byte)) => self.text.push(char::from(byte)),
+                Some(Received::Frame(Ok(payload))) => self.frames.push_back(payload.to_vec()),
+                // Damaged frames are as good as lost
+                Some(Received::Frame(Err(_))) | None => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use app::message::Rejection;
+    use app::shell::{Args, Command, Error, Shell};
+    use std::fmt;
+
+    /// The board's side of the shell, much as the firmware has it.
+    #[derive(Default)]
+    struct Board {
+        output: Vec<u8>,
+        image: Image,
+        watching_buttons: bool,
+    }
+
+    impl fmt::Write for Board {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.output.extend(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    static COMMANDS: [Command<Board>; 2] = [
+        Command {
+            name: "led",
+            usage: "image <5 rows of 0-9>",
+            help: "Control the LED matrix",
+            run: |board, mut args| {
+                if args.required()? != "image" {
+                    return Err(Error::InvalidArgument);
+                }
+                board.image = Image::from_rows(args).ok_or(Error::InvalidArgument)?;
+                Ok(())
+            },
+        },
+        Command {
+            name: "button",
+            usage: "watch on|off",
+            help: "Report button events",
+            run: |board, mut args: Args| {
+                args.required()?;
+                board.watching_buttons = args.required()? == "on";
+                args.end()
+            },
+        },
+    ];
+
+    impl Board {
+        fn answer(&mut self, bytes: &[u8]) -> Message<Response> {
+            let request = match Message::<Request>::decode(bytes) {
+                Ok(request) => request,
+                Err(error) => {
+                    return Message {
+                        seq: app::message::sequence(bytes).unwrap_or(0),
+                        body: Response::Rejected(Rejection::from(error)),
+                    }
+                }
+            };
+            let body = match request.body {
+                Request::Ping => Response::Pong,
+                Request::GetTemperature => Response::Temperature(2_125),
+                Request::SetImage(image) => {
+                    self.image = image;
+                    Response::Done
+                }
+                _ => Response::Rejected(Rejection::UnknownType),
+            };
+            Message {
+                seq: request.seq,
+                body,
+            }
+        }
+    }
+
+    /// A board in a loop back, answering each write in time for the next
+    /// read.
+    struct FakeBoard {
+        board: Board,
+        shell: Shell<Board, 64>,
+        demux: Demux,
+        button_events: VecDeque<&'static str>,
+    }
+
+    impl FakeBoard {
+        fn new() -> Self {
+            Self {
+                board: Board::default(),
+                shell: Shell::new(&COMMANDS),
+                demux: Demux::new(),
+                button_events: VecDeque::new(),
+            }
+        }
+    }
+
+    impl Write for FakeBoard {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            for byte in buf {
+                match self.demux.feed(*byte) {
+                    Some(Received::Text(byte)) => self.shell.feed(&mut self.board, byte).unwrap(),
+                    Some(Received::Frame(Ok(payload))) => {
+                        let response = self.board.answer(payload);
+                        let mut message = [0; MAX_MESSAGE_LEN];
+                        let len = response.encode(&mut message).unwrap();
+                        let mut buffer = [0; MAX_FRAME_LEN];
+                        let len = frame::encode(&message[..len], &mut buffer).unwrap();
+                        self.board.output.extend(&buffer[..len]);
+                    }
+                    Some(Received::Frame(Err(error))) => panic!("{:?}", error),
+                    None => (),
+                }
+            }
+            if self.board.watching_buttons {
+                for event in self.button_events.drain(..) {
+                    self.board
+                        .output
+                        .extend(format!("button: {}\r\n", event).bytes());
+                }
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for FakeBoard {
+        /// A few bytes at a time, as a real port would.
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.board.output.len()).min(7);
+            buf[..len].copy_from_slice(&self.board.output[..len]);
+            self.board.output.drain(..len);
+            Ok(len)
+        }
+    }
+
+    fn connect() -> Device<FakeBoard> {
+        let mut board = FakeBoard::new();
+        // What the board says on starting up
+        board.board.output.extend(b"Hello, World!\r\n> half typed");
+        let mut device = Device::new(board);
+        device.sync().unwrap();
+        device
+    }
+
+    #[test]
+    fn test_command_output() {
+        let mut device = connect();
+        assert_eq!(
+            device.command("help").unwrap(),
+            "help\r\n    List the commands\r\n\
+             led image <5 rows of 0-9>\r\n    Control the LED matrix\r\n\
+             button watch on|off\r\n    Report button events\r\n"
+        );
+        assert_eq!(
+            device.command("nope").unwrap(),
+            "nope: unknown command, try help\r\n"
+        );
+        assert_eq!(device.command("").unwrap(), "");
+    }
+
+    #[test]
+    fn test_unprintable_commands_are_refused() {
+        let mut device = connect();
+        let error = device.command("led\rimage").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_set_image() {
+        let mut device = connect();
+        let image: Image = "..#..\n.###.\n#####\n..#..\n..#..".parse().unwrap();
+        assert_eq!(device.set_image(&image).unwrap(), "");
+        assert_eq!(device.port().board.image, image);
+    }
+
+    #[test]
+    fn test_requests() {
+        let mut device = connect();
+        assert_eq!(device.request(Request::Ping).unwrap(), Response::Pong);
+        assert_eq!(
+            device.request(Request::GetTemperature).unwrap(),
+            Response::Temperature(2_125)
+        );
+        let image = Image::from_bits(&[0b01010; 5], 3);
+        assert_eq!(
+            device.request(Request::SetImage(image)).unwrap(),
+            Response::Done
+        );
+        assert_eq!(device.port().board.image, image);
+    }
+
+    #[test]
+    fn test_stale_responses_are_passed_over() {
+        let mut device = connect();
+        // The answer to an earlier request that was given up on
+        let stale = Message {
+            seq: 0,
+            body: Response::Done,
+        };
+        let mut message = [0; MAX_MESSAGE_LEN];
+        let len = stale.encode(&mut message).unwrap();
+        let mut buffer = [0; MAX_FRAME_LEN];
+        let len = frame::encode(&message[..len], &mut buffer).unwrap();
+        device.port.board.output.extend(&buffer[..len]);
+        assert_eq!(device.request(Request::Ping).unwrap(), Response::Pong);
+    }
+
+    #[test]
+    fn test_malformed_frames_are_rejected() {
+        let mut device = connect();
+        let reply = device.exchange(b"\x01\x07\x00\x7f").unwrap();
+        assert_eq!(
+            Message::<Response>::decode(&reply),
+            Ok(Message {
+                seq: 7,
+                body: Response::Rejected(Rejection::UnknownType)
+            })
+        );
+    }
+
+    #[test]
+    fn test_no_reply_times_out() {
+        let mut device = Device::new(io::Cursor::new(Vec::new()));
+        assert_eq!(device.sync().unwrap_err().kind(), ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_watch_buttons() {
+        let mut board = FakeBoard::new();
+        board
+            .button_events
+            .extend(["Button(A, Pressed)", "Chord", "Button(A, Released)"].iter());
+        let mut device = Device::new(board);
+        device.sync().unwrap();
+
+        let mut events = Vec::new();
+        device
+            .watch_buttons(|event| {
+                events.push(event.to_string());
+                events.len() < 2
+            })
+            .unwrap();
+        assert_eq!(events, vec!["Button(A, Pressed)", "Chord"]);
+        assert!(!device.port().board.watching_buttons);
+    }
+}
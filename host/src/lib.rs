@@ -0,0 +1,4 @@
+//! The host side of talking to the board over its serial port, sharing the
+//! shell, framing and image code of the `app` crate with the firmware.
+
+pub mod device;
@@ -0,0 +1,144 @@
+//! A command line tool for the board, over the serial port that its
+//! interface chip bridges to USB.
+
+use app::{
+    build_info::BuildInfo,
+    display::Image,
+    message::{Request, Response},
+};
+use microrust_host::device::Device;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::process::{self, Command};
+
+const USAGE: &str = "\
+usage: microrust-host <port> <command>
+       microrust-host build-info <file>
+
+commands:
+    shell <line>...    Run a shell command on the board
+    image <file>|-     Show a picture of five rows of five 0-9, . or # characters
+    buttons            Print button events as they happen
+    scroll <text>...   Scroll text across the display
+    ping               Check that the board answers requests
+    version            Show the firmware version
+    temperature        Show the chip temperature
+
+build-info shows what firmware was built from, found in its ELF file or in a
+dump of the board's flash.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+    let result = match args.as_slice() {
+        [command, path] if command == "build-info" => build_info(path),
+        _ => run(&args[0], &args[1], &args[2..]),
+    };
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run(port: &str, command: &str, args: &[String]) -> io::Result<()> {
+    let mut device = Device::new(open(port)?);
+    device.sync()?;
+    match command {
+        "shell" => print!("{}", device.command(&args.join(" "))?),
+        "image" => {
+            let picture = match args {
+                [path] if path == "-" => read_to_string(io::stdin())?,
+                [path] => read_to_string(File::open(path)?)?,
+                _ => return Err(invalid("image takes one file")),
+            };
+            let image: Image = picture
+                .parse()
+                .map_err(|_| invalid("a picture is five rows of five 0-9, . or # characters"))?;
+            print!("{}", device.set_image(&image)?);
+        }
+        "buttons" => device.watch_buttons(|event| {
+            println!("{}", event);
+            true
+        })?,
+        "scroll" => expect_done(device.request(Request::ScrollText(&args.join(" ")))?)?,
+        "ping" => match device.request(Request::Ping)? {
+            Response::Pong => println!("pong"),
+            response => return Err(unexpected(response)),
+        },
+        "version" => match device.request(Request::GetVersion)? {
+            Response::Version {
+                major,
+                minor,
+                patch,
+            } => println!("{}.{}.{}", major, minor, patch),
+            response => return Err(unexpected(response)),
+        },
+        "temperature" => match device.request(Request::GetTemperature)? {
+            Response::Temperature(centi_celsius) => {
+                println!("{:.2} \u{b0}C", f64::from(centi_celsius) / 100.0)
+            }
+            response => return Err(unexpected(response)),
+        },
+        _ => return Err(invalid(USAGE)),
+    }
+    Ok(())
+}
+
+fn build_info(path: &str) -> io::Result<()> {
+    match BuildInfo::find(&fs::read(path)?) {
+        Some(info) => {
+            println!("{}", info);
+            Ok(())
+        }
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no build information in {}", path),
+        )),
+    }
+}
+
+/// Open a serial port at the board's 115200 baud, set up so that reads give
+/// up after a second without data.
+///
+/// The port is set up by `stty` through the descriptor opened here, as some
+/// systems, such as macOS, reset a port's settings once the last descriptor
+/// on it closes. This needs a Unix with `stty`; Windows isn't supported.
+fn open(port: &str) -> io::Result<File> {
+    let file = OpenOptions::new().read(true).write(true).open(port)?;
+    let status = Command::new("stty")
+        .args(["115200", "raw", "-echo", "min", "0", "time", "10"])
+        .stdin(file.try_clone()?)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("could not set up {}", port)));
+    }
+    Ok(file)
+}
+
+fn read_to_string(mut reader: impl Read) -> io::Result<String> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+fn expect_done(response: Response) -> io::Result<()> {
+    match response {
+        Response::Done => Ok(()),
+        response => Err(unexpected(response)),
+    }
+}
+
+fn unexpected(response: Response) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected response: {:?}", response),
+    )
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
@@ -0,0 +1,385 @@
+//! Generates the linker's `memory.x` for the chip on the board being built
+//! for, less any memory reserved for others, and captures what the firmware
+//! is being built from for it to report.
+//!
+//! Flash is laid out from the bottom as the MBR and SoftDevice, this
+//! firmware, and then the bootloader, with the settings pages either just
+//! before or after the bootloader. The last `BUILD_INFO_SIZE` bytes of the
+//! firmware's flash hold its `BuildInfo`, at an address that only the
+//! layout decides. RAM is laid out as the SoftDevice's, this firmware's,
+//! and a retained region at the top that is never initialised, so that
+//! what is kept there survives a reset, and stays put however the
+//! firmware's own use of RAM changes.
+//!
+//! Nothing is reserved by default but the retained region. Each reservation
+//! is read from the environment, such as from `[env]` in
+//! `.cargo/config.toml`, as bytes in decimal, in hex with `0x`, or in KiB
+//! with `K`:
+//!
+//! * `SOFTDEVICE_FLASH` and `SOFTDEVICE_RAM`: what the SoftDevice, and the
+//!   MBR before it, take from the bottom of each.
+//! * `BOOTLOADER_OFFSET`: the address the bootloader starts at. Everything
+//!   above it but the settings pages is left to the bootloader.
+//! * `SETTINGS_PAGES`: the first and last flash pages kept for settings,
+//!   such as `126-127`, either side of the bootloader but not within it.
+//! * `NOINIT_RAM`: the size of the retained region, 1K unless set.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::process::Command;
+
+const FLASH_ORIGIN: u32 = 0x0000_0000;
+const RAM_ORIGIN: u32 = 0x2000_0000;
+const DEFAULT_NOINIT_RAM: u32 = 1024;
+const BUILD_INFO_SIZE: u32 = 256;
+/// Less than this leaves too little for the stack to be worth running.
+const MIN_RAM: u32 = 4 * 1024;
+
+struct Chip {
+    name: &'static str,
+    target: &'static str,
+    flash: u32,
+    ram: u32,
+    page_size: u32,
+}
+
+const NRF51822_XXAA: Chip = Chip {
+    name: "nRF51822_xxAA",
+    target: "thumbv6m-none-eabi",
+    flash: 256 * 1024,
+    ram: 16 * 1024,
+    page_size: 1024,
+};
+
+const NRF52833_XXAA: Chip = Chip {
+    name: "nRF52833_xxAA",
+    target: "thumbv7em-none-eabihf",
+    flash: 512 * 1024,
+    ram: 128 * 1024,
+    page_size: 4096,
+};
+
+/// Memory kept from this firmware for others.
+struct Reserved {
+    softdevice_flash: u32,
+    softdevice_ram: u32,
+    bootloader_offset: Option<u32>,
+    settings_pages: Option<RangeInclusive<u32>>,
+    noinit_ram: u32,
+}
+
+fn main() {
+    let v1 = env::var_os("CARGO_FEATURE_V1").is_some();
+    let v2 = env::var_os("CARGO_FEATURE_V2").is_some();
+    let chip = match (v1, v2) {
+        (true, false) => NRF51822_XXAA,
+        (false, true) => NRF52833_XXAA,
+        _ => panic!("Exactly one of the `v1` and `v2` features must be enabled"),
+    };
+
+    // Building for the host, such as for `cargo test --workspace`, never
+    // links the firmware, so only a mismatched board and core is an error
+    let target = env::var("TARGET").unwrap();
+    if target.starts_with("thumb") && target != chip.target {
+        panic!(
+            "The {}'s firmware is built for {}, not {}",
+            chip.name, chip.target, target
+        );
+    }
+
+    let reserved = reserved().unwrap_or_else(|error| panic!("{}", error));
+    let memory =
+        memory_x(&chip, &reserved).unwrap_or_else(|error| panic!("Bad memory layout: {}", error));
+
+    // Put the linker script somewhere the linker can find it
+    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(out.join("memory.x"), memory).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-changed=build.rs");
+
+    capture_build_info();
+}
+
+/// Hand the commit, whether the tree is dirty, the profile and the features
+/// to the firmware as `BUILD_COMMIT`, `BUILD_DIRTY` (empty if clean),
+/// `BUILD_PROFILE` and `BUILD_FEATURES`. Outside of git the commit is left
+/// empty.
+fn capture_build_info() {
+    let commit = git(&["rev-parse", "HEAD"]).unwrap_or_default();
+    let dirty = !commit.is_empty()
+        && git(&["status", "--porcelain", "--untracked-files=no"])
+            .is_some_and(|status| !status.is_empty());
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(name, _)| {
+            let feature = name.strip_prefix("CARGO_FEATURE_")?;
+            Some(feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=BUILD_COMMIT={}", commit);
+    println!(
+        "cargo:rustc-env=BUILD_DIRTY={}",
+        if dirty { "1" } else { "" }
+    );
+    println!(
+        "cargo:rustc-env=BUILD_PROFILE={}",
+        env::var("PROFILE").unwrap()
+    );
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features.join(","));
+
+    // Run again on a new commit, and on any change to the source that may
+    // make the tree dirty
+    for path in [
+        git(&["rev-parse", "--git-path", "HEAD"]),
+        git(&["rev-parse", "--git-path", "index"]),
+    ]
+    .iter()
+    .flatten()
+    {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+    if let Some(head) = git(&["symbolic-ref", "-q", "HEAD"]) {
+        if let Some(path) = git(&["rev-parse", "--git-path", &head]) {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=../app/src");
+}
+
+/// What a git command prints, trimmed, or nothing should it fail.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
+
+fn reserved() -> Result<Reserved, String> {
+    Ok(Reserved {
+        softdevice_flash: size_var("SOFTDEVICE_FLASH")?.unwrap_or(0),
+        softdevice_ram: size_var("SOFTDEVICE_RAM")?.unwrap_or(0),
+        bootloader_offset: size_var("BOOTLOADER_OFFSET")?,
+        settings_pages: pages_var("SETTINGS_PAGES")?,
+        noinit_ram: size_var("NOINIT_RAM")?.unwrap_or(DEFAULT_NOINIT_RAM),
+    })
+}
+
+fn var(name: &str) -> Option<String> {
+    println!("cargo:rerun-if-env-changed={}", name);
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+fn size_var(name: &str) -> Result<Option<u32>, String> {
+    var(name)
+        .map(|value| parse_size(&value).ok_or_else(|| format!("{} isn't a size: {}", name, value)))
+        .transpose()
+}
+
+fn pages_var(name: &str) -> Result<Option<RangeInclusive<u32>>, String> {
+    var(name)
+        .map(|value| {
+            let (first, last) = value.split_once('-').unwrap_or((&value, &value));
+            match (first.trim().parse(), last.trim().parse()) {
+                (Ok(first), Ok(last)) => Ok(first..=last),
+                _ => Err(format!(
+                    "{} isn't a page or range of pages: {}",
+                    name, value
+                )),
+            }
+        })
+        .transpose()
+}
+
+/// Bytes in decimal, in hex with `0x`, or in KiB with `K`.
+fn parse_size(value: &str) -> Option<u32> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix("0x") {
+        u32::from_str_radix(&hex.replace('_', ""), 16).ok()
+    } else if let Some(kib) = value.strip_suffix('K') {
+        kib.trim().parse::<u32>().ok()?.checked_mul(1024)
+    } else {
+        value.parse().ok()
+    }
+}
+
+fn memory_x(chip: &Chip, reserved: &Reserved) -> Result<String, String> {
+    let page_size = chip.page_size;
+    let check_page_aligned = |what: &str, address: u32| {
+        if address.is_multiple_of(page_size) {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} of {:#x} isn't on a {} byte flash page boundary",
+                what, address, page_size
+            ))
+        }
+    };
+
+    // Flash, with the firmware from the end of the SoftDevice up to the
+    // first of the bootloader or settings pages above it
+    check_page_aligned("SOFTDEVICE_FLASH", reserved.softdevice_flash)?;
+    if reserved.softdevice_flash > chip.flash {
+        return Err(format!(
+            "SOFTDEVICE_FLASH of {:#x} is more than the {}'s {}K of flash",
+            reserved.softdevice_flash,
+            chip.name,
+            chip.flash / 1024
+        ));
+    }
+    let flash_start = FLASH_ORIGIN + reserved.softdevice_flash;
+    let mut flash_end = chip.flash;
+    if let Some(offset) = reserved.bootloader_offset {
+        check_page_aligned("BOOTLOADER_OFFSET", offset)?;
+        if offset > chip.flash {
+            return Err(format!(
+                "BOOTLOADER_OFFSET of {:#x} is past the end of the {}'s {}K of flash",
+                offset,
+                chip.name,
+                chip.flash / 1024
+            ));
+        }
+        flash_end = offset;
+    }
+    let settings = match &reserved.settings_pages {
+        Some(pages) => {
+            let (first, last) = (*pages.start(), *pages.end());
+            if first > last {
+                return Err(format!("SETTINGS_PAGES runs backwards, {}-{}", first, last));
+            }
+            let start = u64::from(first) * u64::from(page_size);
+            let end = (u64::from(last) + 1) * u64::from(page_size);
+            if end > u64::from(chip.flash) {
+                return Err(format!(
+                    "SETTINGS_PAGES {}-{} run past the end of the {}'s {}K of flash",
+                    first,
+                    last,
+                    chip.name,
+                    chip.flash / 1024
+                ));
+            }
+            if start < u64::from(flash_start) {
+                return Err(format!(
+                    "SETTINGS_PAGES {}-{} overlap the SoftDevice, which ends at {:#x}",
+                    first, last, flash_start
+                ));
+            }
+            if let Some(offset) = reserved.bootloader_offset.map(u64::from) {
+                if start < offset && end > offset {
+                    return Err(format!(
+                        "SETTINGS_PAGES {}-{} run into the bootloader at {:#x}",
+                        first, last, offset
+                    ));
+                }
+            }
+            let (start, end) = (start as u32, end as u32);
+            flash_end = flash_end.min(start);
+            Some((start, end))
+        }
+        None => None,
+    };
+    if flash_start
+        .checked_add(BUILD_INFO_SIZE)
+        .is_none_or(|end| end >= flash_end)
+    {
+        return Err(format!(
+            "no flash is left for the firmware between {:#x} and {:#x}",
+            flash_start, flash_end
+        ));
+    }
+    let build_info_start = flash_end - BUILD_INFO_SIZE;
+
+    // RAM, from both ends towards the middle
+    if !reserved.softdevice_ram.is_multiple_of(4) {
+        return Err(format!(
+            "SOFTDEVICE_RAM of {:#x} isn't a multiple of 4 bytes",
+            reserved.softdevice_ram
+        ));
+    }
+    // Keeps the top of the stack, just below, 8 byte aligned
+    if reserved.noinit_ram == 0 || !reserved.noinit_ram.is_multiple_of(8) {
+        return Err(format!(
+            "NOINIT_RAM of {} bytes must be a non-zero multiple of 8 bytes, as \
+             the crash, fault and watchdog records are kept there",
+            reserved.noinit_ram
+        ));
+    }
+    let ram = reserved
+        .softdevice_ram
+        .checked_add(reserved.noinit_ram)
+        .and_then(|reserved| chip.ram.checked_sub(reserved))
+        .filter(|ram| *ram >= MIN_RAM)
+        .ok_or_else(|| {
+            format!(
+                "less than {} bytes of the {}'s {}K of RAM are left once {} bytes go \
+                 to the SoftDevice and {} are retained",
+                MIN_RAM,
+                chip.name,
+                chip.ram / 1024,
+                reserved.softdevice_ram,
+                reserved.noinit_ram
+            )
+        })?;
+    let ram_start = RAM_ORIGIN + reserved.softdevice_ram;
+    let noinit_start = ram_start + ram;
+
+    let mut memory = format!(
+        "/* Generated by nrf-app/build.rs for the {} */
+MEMORY
+{{
+  FLASH : ORIGIN = {:#010x}, LENGTH = {:#x}
+  BUILD_INFO : ORIGIN = {:#010x}, LENGTH = {:#x}
+  RAM : ORIGIN = {:#010x}, LENGTH = {:#x}
+  NOINIT : ORIGIN = {:#010x}, LENGTH = {:#x}
+}}
+
+SECTIONS
+{{
+  /* What the firmware was built from, wherever its own flash ends */
+  .build_info :
+  {{
+    KEEP(*(.build_info));
+  }} > BUILD_INFO
+
+  /* Kept across resets, wherever the firmware's own RAM ends */
+  .noinit (NOLOAD) : ALIGN(4)
+  {{
+    KEEP(*(.noinit .noinit.*));
+  }} > NOINIT
+}}
+INSERT BEFORE .uninit;
+",
+        chip.name,
+        flash_start,
+        build_info_start - flash_start,
+        build_info_start,
+        BUILD_INFO_SIZE,
+        ram_start,
+        ram,
+        noinit_start,
+        reserved.noinit_ram
+    );
+
+    // Where the reservations are, for the firmware to find
+    let mut symbols = String::new();
+    if let Some((start, end)) = settings {
+        let _ = writeln!(
+            symbols,
+            "_settings_start = {:#010x};\n_settings_end = {:#010x};",
+            start, end
+        );
+    }
+    if let Some(offset) = reserved.bootloader_offset {
+        let _ = writeln!(symbols, "_bootloader_start = {:#010x};", offset);
+    }
+    if !symbols.is_empty() {
+        memory.push('\n');
+        memory.push_str(&symbols);
+    }
+    Ok(memory)
+}
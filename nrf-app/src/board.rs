@@ -0,0 +1,12 @@
+//! The pins and peripherals of the version of the micro:bit being built
+//! for, chosen by the `v1` and `v2` features.
+
+#[cfg(feature = "v1")]
+mod v1;
+#[cfg(feature = "v1")]
+pub use v1::*;
+
+#[cfg(feature = "v2")]
+mod v2;
+#[cfg(feature = "v2")]
+pub use v2::*;
@@ -0,0 +1,208 @@
+//! Everything on the micro:bit v1 that the nRF51822 is wired to, by name.
+//!
+//! Each pin is handed out as its own type in the mode it needs, so using it
+//! for something else, or twice, fails to compile rather than to work.
+//! Pins shared between the edge connector and the parts on the board, such
+//! as the display's columns and the buttons, are only handed out once, as
+//! those parts.
+//!
+//! Unlike the v2, the v1 has a single I2C bus, shared between the motion
+//! sensor and the edge connector's pins 19 and 20, and has no speaker,
+//! microphone or touch sensitive logo.
+
+// Not every part of the board is used by this firmware
+#![allow(dead_code)]
+
+use app::matrix::{self, Layout};
+use hal::{
+    gpio::{
+        p0::{self, *},
+        Disconnected, Floating, Input, Level, Output, Pin, PushPull,
+    },
+    pac::{Peripherals, ADC, CLOCK, GPIOTE, POWER, RTC1, TEMP, TIMER1, TWI0, UART0, WDT},
+    twi, uart,
+};
+
+pub const MATRIX_ROWS: usize = matrix::V1_ROWS;
+pub const MATRIX_COLS: usize = matrix::V1_COLS;
+
+/// Which pixel of the display each row and column pin lights.
+pub static LAYOUT: Layout<MATRIX_ROWS, MATRIX_COLS> = matrix::V1;
+
+/// A row or column of the LED matrix, as the display drives them.
+pub type LedPin = Pin<Output<PushPull>>;
+
+/// The LED matrix, which is lit a row at a time. Its three rows of nine
+/// columns are wired across the 5x5 grid as `LAYOUT` describes. Rows are
+/// active high and columns active low, so everything starts off.
+pub struct DisplayPins {
+    pub row1: P0_13<Output<PushPull>>,
+    pub row2: P0_14<Output<PushPull>>,
+    pub row3: P0_15<Output<PushPull>>,
+    pub col1: P0_04<Output<PushPull>>,
+    pub col2: P0_05<Output<PushPull>>,
+    pub col3: P0_06<Output<PushPull>>,
+    pub col4: P0_07<Output<PushPull>>,
+    pub col5: P0_08<Output<PushPull>>,
+    pub col6: P0_09<Output<PushPull>>,
+    pub col7: P0_10<Output<PushPull>>,
+    pub col8: P0_11<Output<PushPull>>,
+    pub col9: P0_12<Output<PushPull>>,
+}
+
+impl DisplayPins {
+    /// The rows and then the columns, in the order `LAYOUT` numbers them.
+    pub fn degrade(self) -> ([LedPin; MATRIX_ROWS], [LedPin; MATRIX_COLS]) {
+        (
+            [
+                self.row1.degrade(),
+                self.row2.degrade(),
+                self.row3.degrade(),
+            ],
+            [
+                self.col1.degrade(),
+                self.col2.degrade(),
+                self.col3.degrade(),
+                self.col4.degrade(),
+                self.col5.degrade(),
+                self.col6.degrade(),
+                self.col7.degrade(),
+                self.col8.degrade(),
+                self.col9.degrade(),
+            ],
+        )
+    }
+}
+
+/// Buttons A and B, which pull their pins low when pressed.
+pub struct Buttons {
+    pub a: P0_17<Input<Floating>>,
+    pub b: P0_26<Input<Floating>>,
+}
+
+/// The UART that the interface chip bridges to USB.
+pub struct UartPins {
+    pub tx: P0_24<Output<PushPull>>,
+    pub rx: P0_25<Input<Floating>>,
+}
+
+impl From<UartPins> for uart::Pins {
+    fn from(pins: UartPins) -> Self {
+        Self {
+            txd: pins.tx.degrade(),
+            rxd: pins.rx.degrade(),
+            cts: None,
+            rts: None,
+        }
+    }
+}
+
+/// The I2C bus to the motion sensor, which is also on the edge connector.
+pub struct I2cPins {
+    pub scl: P0_00<Input<Floating>>,
+    pub sda: P0_30<Input<Floating>>,
+    /// Pulled low by the accelerometer when it has news.
+    pub int: P0_28<Input<Floating>>,
+}
+
+impl From<I2cPins> for twi::Pins {
+    fn from(pins: I2cPins) -> Self {
+        Self {
+            scl: pins.scl.degrade(),
+            sda: pins.sda.degrade(),
+        }
+    }
+}
+
+/// The edge connector's pins that aren't already taken by the parts on the
+/// board, left disconnected until given a use.
+pub struct EdgeConnector {
+    pub p0: P0_03<Disconnected>,
+    pub p1: P0_02<Disconnected>,
+    pub p2: P0_01<Disconnected>,
+    pub p8: P0_18<Disconnected>,
+    pub p12: P0_20<Disconnected>,
+    pub p13: P0_23<Disconnected>,
+    pub p14: P0_22<Disconnected>,
+    pub p15: P0_21<Disconnected>,
+    pub p16: P0_16<Disconnected>,
+}
+
+/// The board's pins, and the peripherals this firmware drives.
+#[allow(non_snake_case)]
+pub struct Board {
+    pub display: DisplayPins,
+    pub buttons: Buttons,
+    pub uart: UartPins,
+    pub i2c: I2cPins,
+    pub edge: EdgeConnector,
+
+    pub ADC: ADC,
+    pub CLOCK: CLOCK,
+    pub GPIOTE: GPIOTE,
+    pub POWER: POWER,
+    pub RTC1: RTC1,
+    pub TEMP: TEMP,
+    pub TIMER1: TIMER1,
+    pub TWI0: TWI0,
+    pub UART0: UART0,
+    pub WDT: WDT,
+}
+
+impl Board {
+    pub fn new(p: Peripherals) -> Self {
+        let p0 = p0::Parts::new(p.GPIO);
+        Self {
+            display: DisplayPins {
+                row1: p0.p0_13.into_push_pull_output(Level::Low),
+                row2: p0.p0_14.into_push_pull_output(Level::Low),
+                row3: p0.p0_15.into_push_pull_output(Level::Low),
+                col1: p0.p0_04.into_push_pull_output(Level::High),
+                col2: p0.p0_05.into_push_pull_output(Level::High),
+                col3: p0.p0_06.into_push_pull_output(Level::High),
+                col4: p0.p0_07.into_push_pull_output(Level::High),
+                col5: p0.p0_08.into_push_pull_output(Level::High),
+                col6: p0.p0_09.into_push_pull_output(Level::High),
+                col7: p0.p0_10.into_push_pull_output(Level::High),
+                col8: p0.p0_11.into_push_pull_output(Level::High),
+                col9: p0.p0_12.into_push_pull_output(Level::High),
+            },
+            buttons: Buttons {
+                a: p0.p0_17.into_floating_input(),
+                b: p0.p0_26.into_floating_input(),
+            },
+            uart: UartPins {
+                // Idles high
+                tx: p0.p0_24.into_push_pull_output(Level::High),
+                rx: p0.p0_25.into_floating_input(),
+            },
+            i2c: I2cPins {
+                scl: p0.p0_00.into_floating_input(),
+                sda: p0.p0_30.into_floating_input(),
+                int: p0.p0_28.into_floating_input(),
+            },
+            edge: EdgeConnector {
+                p0: p0.p0_03,
+                p1: p0.p0_02,
+                p2: p0.p0_01,
+                p8: p0.p0_18,
+                p12: p0.p0_20,
+                p13: p0.p0_23,
+                p14: p0.p0_22,
+                p15: p0.p0_21,
+                p16: p0.p0_16,
+            },
+
+            ADC: p.ADC,
+            CLOCK: p.CLOCK,
+            GPIOTE: p.GPIOTE,
+            POWER: p.POWER,
+            RTC1: p.RTC1,
+            TEMP: p.TEMP,
+            TIMER1: p.TIMER1,
+            TWI0: p.TWI0,
+            UART0: p.UART0,
+            WDT: p.WDT,
+        }
+    }
+}
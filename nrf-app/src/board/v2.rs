@@ -0,0 +1,245 @@
+//! Everything on the micro:bit v2 that the nRF52833 is wired to, by name.
+//!
+//! Each pin is handed out as its own type in the mode it needs, so using it
+//! for something else, or twice, fails to compile rather than to work.
+//! Pins shared between the edge connector and the parts on the board, such
+//! as the display's columns and the buttons, are only handed out once, as
+//! those parts.
+
+// Not every part of the board is used by this firmware
+#![allow(dead_code)]
+
+use app::{
+    display::{COLS, ROWS},
+    matrix::{self, Layout},
+};
+use hal::{
+    gpio::{
+        p0::{self, *},
+        p1::{self, *},
+        Disconnected, Floating, Input, Level, Output, Pin, PushPull,
+    },
+    pac::{Peripherals, CLOCK, GPIOTE, POWER, PWM0, RTC1, SAADC, TEMP, TIMER1, TWIM0, UARTE0, WDT},
+    twim, uarte,
+};
+
+pub const MATRIX_ROWS: usize = ROWS;
+pub const MATRIX_COLS: usize = COLS;
+
+/// Which pixel of the display each row and column pin lights.
+pub static LAYOUT: Layout<MATRIX_ROWS, MATRIX_COLS> = matrix::V2;
+
+/// A row or column of the LED matrix, as the display drives them.
+pub type LedPin = Pin<Output<PushPull>>;
+
+/// The LED matrix, which is lit a row at a time. Rows are active high and
+/// columns active low, so everything starts off.
+pub struct DisplayPins {
+    pub row1: P0_21<Output<PushPull>>,
+    pub row2: P0_22<Output<PushPull>>,
+    pub row3: P0_15<Output<PushPull>>,
+    pub row4: P0_24<Output<PushPull>>,
+    pub row5: P0_19<Output<PushPull>>,
+    pub col1: P0_28<Output<PushPull>>,
+    pub col2: P0_11<Output<PushPull>>,
+    pub col3: P0_31<Output<PushPull>>,
+    pub col4: P1_05<Output<PushPull>>,
+    pub col5: P0_30<Output<PushPull>>,
+}
+
+impl DisplayPins {
+    /// The rows and then the columns, top to bottom and left to right.
+    pub fn degrade(self) -> ([LedPin; MATRIX_ROWS], [LedPin; MATRIX_COLS]) {
+        (
+            [
+                self.row1.degrade(),
+                self.row2.degrade(),
+                self.row3.degrade(),
+                self.row4.degrade(),
+                self.row5.degrade(),
+            ],
+            [
+                self.col1.degrade(),
+                self.col2.degrade(),
+                self.col3.degrade(),
+                self.col4.degrade(),
+                self.col5.degrade(),
+            ],
+        )
+    }
+}
+
+/// Buttons A and B, which pull their pins low when pressed.
+pub struct Buttons {
+    pub a: P0_14<Input<Floating>>,
+    pub b: P0_23<Input<Floating>>,
+}
+
+/// The UART that the interface chip bridges to USB.
+pub struct UartPins {
+    pub tx: P0_06<Output<PushPull>>,
+    pub rx: P1_08<Input<Floating>>,
+}
+
+impl From<UartPins> for uarte::Pins {
+    fn from(pins: UartPins) -> Self {
+        Self {
+            txd: pins.tx.degrade(),
+            rxd: pins.rx.degrade(),
+            cts: None,
+            rts: None,
+        }
+    }
+}
+
+/// The I2C bus to the motion sensor and the interface chip.
+pub struct InternalI2cPins {
+    pub scl: P0_08<Input<Floating>>,
+    pub sda: P0_16<Input<Floating>>,
+    /// Pulled low by the devices on the bus when they have news.
+    pub int: P0_25<Input<Floating>>,
+}
+
+/// The I2C bus on the edge connector's pins 19 and 20.
+pub struct ExternalI2cPins {
+    pub scl: P0_26<Input<Floating>>,
+    pub sda: P1_00<Input<Floating>>,
+}
+
+impl From<InternalI2cPins> for twim::Pins {
+    fn from(pins: InternalI2cPins) -> Self {
+        Self {
+            scl: pins.scl.degrade(),
+            sda: pins.sda.degrade(),
+        }
+    }
+}
+
+impl From<ExternalI2cPins> for twim::Pins {
+    fn from(pins: ExternalI2cPins) -> Self {
+        Self {
+            scl: pins.scl.degrade(),
+            sda: pins.sda.degrade(),
+        }
+    }
+}
+
+/// The microphone, which only draws power while `run` is high. Its level
+/// is read from `input` with the SAADC.
+pub struct Microphone {
+    pub input: P0_05<Input<Floating>>,
+    pub run: P0_20<Output<PushPull>>,
+}
+
+/// The edge connector's pins that aren't already taken by the parts on the
+/// board, left disconnected until given a use. Pins 8 and 9 are the NFC
+/// antenna's unless the UICR's NFCPINS says otherwise.
+pub struct EdgeConnector {
+    pub p0: P0_02<Disconnected>,
+    pub p1: P0_03<Disconnected>,
+    pub p2: P0_04<Disconnected>,
+    pub p8: P0_10<Disconnected>,
+    pub p9: P0_09<Disconnected>,
+    pub p12: P0_12<Disconnected>,
+    pub p13: P0_17<Disconnected>,
+    pub p14: P0_01<Disconnected>,
+    pub p15: P0_13<Disconnected>,
+    pub p16: P1_02<Disconnected>,
+}
+
+/// The board's pins, and the peripherals this firmware drives.
+#[allow(non_snake_case)]
+pub struct Board {
+    pub display: DisplayPins,
+    pub buttons: Buttons,
+    pub uart: UartPins,
+    pub internal_i2c: InternalI2cPins,
+    pub external_i2c: ExternalI2cPins,
+    /// The speaker, driven by PWM.
+    pub speaker: P0_00<Output<PushPull>>,
+    pub microphone: Microphone,
+    /// The gold logo on the front, read by its capacitance.
+    pub touch_logo: P1_04<Input<Floating>>,
+    pub edge: EdgeConnector,
+
+    pub CLOCK: CLOCK,
+    pub GPIOTE: GPIOTE,
+    pub POWER: POWER,
+    pub PWM0: PWM0,
+    pub RTC1: RTC1,
+    pub SAADC: SAADC,
+    pub TEMP: TEMP,
+    pub TIMER1: TIMER1,
+    pub TWIM0: TWIM0,
+    pub UARTE0: UARTE0,
+    pub WDT: WDT,
+}
+
+impl Board {
+    pub fn new(p: Peripherals) -> Self {
+        let p0 = p0::Parts::new(p.P0);
+        let p1 = p1::Parts::new(p.P1);
+        Self {
+            display: DisplayPins {
+                row1: p0.p0_21.into_push_pull_output(Level::Low),
+                row2: p0.p0_22.into_push_pull_output(Level::Low),
+                row3: p0.p0_15.into_push_pull_output(Level::Low),
+                row4: p0.p0_24.into_push_pull_output(Level::Low),
+                row5: p0.p0_19.into_push_pull_output(Level::Low),
+                col1: p0.p0_28.into_push_pull_output(Level::High),
+                col2: p0.p0_11.into_push_pull_output(Level::High),
+                col3: p0.p0_31.into_push_pull_output(Level::High),
+                col4: p1.p1_05.into_push_pull_output(Level::High),
+                col5: p0.p0_30.into_push_pull_output(Level::High),
+            },
+            buttons: Buttons {
+                a: p0.p0_14.into_floating_input(),
+                b: p0.p0_23.into_floating_input(),
+            },
+            uart: UartPins {
+                // Idles high
+                tx: p0.p0_06.into_push_pull_output(Level::High),
+                rx: p1.p1_08.into_floating_input(),
+            },
+            internal_i2c: InternalI2cPins {
+                scl: p0.p0_08.into_floating_input(),
+                sda: p0.p0_16.into_floating_input(),
+                int: p0.p0_25.into_floating_input(),
+            },
+            external_i2c: ExternalI2cPins {
+                scl: p0.p0_26.into_floating_input(),
+                sda: p1.p1_00.into_floating_input(),
+            },
+            speaker: p0.p0_00.into_push_pull_output(Level::Low),
+            microphone: Microphone {
+                input: p0.p0_05.into_floating_input(),
+                run: p0.p0_20.into_push_pull_output(Level::Low),
+            },
+            touch_logo: p1.p1_04.into_floating_input(),
+            edge: EdgeConnector {
+                p0: p0.p0_02,
+                p1: p0.p0_03,
+                p2: p0.p0_04,
+                p8: p0.p0_10,
+                p9: p0.p0_09,
+                p12: p0.p0_12,
+                p13: p0.p0_17,
+                p14: p0.p0_01,
+                p15: p0.p0_13,
+                p16: p1.p1_02,
+            },
+
+            CLOCK: p.CLOCK,
+            GPIOTE: p.GPIOTE,
+            POWER: p.POWER,
+            PWM0: p.PWM0,
+            RTC1: p.RTC1,
+            SAADC: p.SAADC,
+            TEMP: p.TEMP,
+            TIMER1: p.TIMER1,
+            TWIM0: p.TWIM0,
+            UARTE0: p.UARTE0,
+            WDT: p.WDT,
+        }
+    }
+}
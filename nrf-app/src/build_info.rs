@@ -0,0 +1,16 @@
+//! What this firmware was built from, as captured by `build.rs`. It is kept
+//! in the flash region that the generated `memory.x` sets aside for it, for
+//! tools to read out of the ELF or a flash dump.
+
+use app::build_info::BuildInfo;
+
+#[used]
+#[no_mangle]
+#[link_section = ".build_info"]
+pub static BUILD_INFO: BuildInfo = BuildInfo::new(
+    env!("CARGO_PKG_VERSION"),
+    env!("BUILD_COMMIT"),
+    !env!("BUILD_DIRTY").is_empty(),
+    env!("BUILD_PROFILE"),
+    env!("BUILD_FEATURES"),
+);
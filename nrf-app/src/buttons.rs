@@ -0,0 +1,78 @@
+//! Samples buttons A and B on each of their edges from the GPIOTE interrupt,
+//! posting the samples to the main loop through a queue.
+
+use crate::clock;
+use app::{button::ButtonId, queue::Producer};
+use hal::{
+    gpio::{Floating, Input, Pin},
+    gpiote::Gpiote,
+    pac::GPIOTE,
+    prelude::*,
+};
+
+pub const SAMPLE_QUEUE_LEN: usize = 16;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ButtonSample {
+    pub id: ButtonId,
+    pub pressed: bool,
+    pub at_ms: u32,
+}
+
+pub type SampleProducer = Producer<'static, ButtonSample, SAMPLE_QUEUE_LEN>;
+
+pub struct ButtonEdges {
+    gpiote: Gpiote,
+    button_a: Pin<Input<Floating>>,
+    button_b: Pin<Input<Floating>>,
+    samples: SampleProducer,
+}
+
+impl ButtonEdges {
+    /// Listen for both edges of each button. The GPIOTE interrupt must be
+    /// unmasked and forwarded to `handle_interrupt`.
+    pub fn new(
+        gpiote: GPIOTE,
+        button_a: Pin<Input<Floating>>,
+        button_b: Pin<Input<Floating>>,
+        samples: SampleProducer,
+    ) -> Self {
+        let gpiote = Gpiote::new(gpiote);
+        gpiote
+            .channel0()
+            .input_pin(&button_a)
+            .toggle()
+            .enable_interrupt();
+        gpiote
+            .channel1()
+            .input_pin(&button_b)
+            .toggle()
+            .enable_interrupt();
+        Self {
+            gpiote,
+            button_a,
+            button_b,
+            samples,
+        }
+    }
+
+    pub fn handle_interrupt(&mut self) {
+        let at_ms = clock::now_ms();
+        let channels = [
+            (self.gpiote.channel0(), ButtonId::A, &self.button_a),
+            (self.gpiote.channel1(), ButtonId::B, &self.button_b),
+        ];
+        for (channel, id, pin) in channels.iter() {
+            if channel.is_event_triggered() {
+                channel.reset_events();
+                let pressed = app::is_result_ok_and_true(pin.is_low());
+                // A full queue counts the drop for the main loop to report
+                let _ = self.samples.enqueue(ButtonSample {
+                    id: *id,
+                    pressed,
+                    at_ms,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! A free running millisecond clock, built on an RTC counting the 32.768 kHz
+//! low frequency clock. The RTC counter is only 24 bits wide, so its
+//! overflows are counted to extend it.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use hal::{
+    pac::RTC1,
+    rtc::{Rtc, RtcInterrupt},
+};
+
+const TICKS_PER_SECOND: u64 = 32_768;
+const COUNTER_BITS: u32 = 24;
+
+static OVERFLOWS: AtomicU32 = AtomicU32::new(0);
+
+pub struct Clock {
+    rtc: Rtc<RTC1>,
+}
+
+impl Clock {
+    /// Start counting from zero. The low frequency clock must already be
+    /// running, and the RTC's interrupt must be unmasked and forwarded to
+    /// `handle_interrupt`.
+    pub fn new(rtc: RTC1) -> Self {
+        let mut rtc = Rtc::new(rtc, 0).unwrap();
+        rtc.enable_interrupt(RtcInterrupt::Overflow, None);
+        rtc.enable_counter();
+        Self { rtc }
+    }
+
+    pub fn handle_interrupt(&mut self) {
+        if self.rtc.is_event_triggered(RtcInterrupt::Overflow) {
+            self.rtc.reset_event(RtcInterrupt::Overflow);
+            // The only writer, so no read-modify-write is needed, which
+            // the v1's Cortex-M0 doesn't have
+            let overflows = OVERFLOWS.load(Ordering::Relaxed);
+            OVERFLOWS.store(overflows + 1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Milliseconds since the clock was started, wrapping after about 49 days.
+pub fn now_ms() -> u32 {
+    let ticks = cortex_m::interrupt::free(|_| {
+        // Safe as we only read, and the clock owns the only writable handle
+        let rtc = unsafe { &*RTC1::ptr() };
+        let mut overflows = OVERFLOWS.load(Ordering::Relaxed);
+        let mut counter = rtc.counter.read().bits();
+        // An overflow that has happened but not yet been handled must be
+        // counted, with the counter read again as it may have wrapped since.
+        if rtc.events_ovrflw.read().bits() != 0 {
+            overflows += 1;
+            counter = rtc.counter.read().bits();
+        }
+        (u64::from(overflows) << COUNTER_BITS) | u64::from(counter)
+    });
+    (ticks * 1_000 / TICKS_PER_SECOND) as u32
+}
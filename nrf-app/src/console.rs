@@ -0,0 +1,401 @@
+//! A command shell on the UART that the interface chip bridges to USB, and
+//! another on RTT for when there is only a debug probe to hand.
+//!
+//! Commands typed over RTT come in on its down channel, and their replies
+//! go out on an up channel of their own so as not to break into the log.
+//!
+//! The same line also carries binary frames for host tools, which are
+//! picked out of the typing by a `Demux`. Each frame holds a request, which
+//! is answered with a response in a frame of its own.
+
+use crate::{
+    build_info::BUILD_INFO,
+    log::MAX_LEVEL as MAX_LOG_LEVEL,
+    motion::Reading,
+    serial::{self, RxConsumer},
+    with_display, SCROLL_COLUMN_MS,
+};
+use app::{
+    button::{ButtonId, PairEvent},
+    display::{Image, COLS, MAX_LEVEL, ROWS},
+    frame::{self, Demux, Received, MAX_FRAME_LEN},
+    log::Level,
+    message::{self, Message, Rejection, Request, Response, MAX_MESSAGE_LEN},
+    reset::ResetReason,
+    shell::{Args, Command, Error, Shell},
+};
+use core::fmt::{self, Write};
+use hal::{pac::SCB, temp::Temp};
+use rtt_target::{DownChannel, UpChannel};
+
+/// The longest command line that can be typed.
+pub const LINE_LEN: usize = 64;
+
+/// Where commands come from, and so where their replies go.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Port {
+    Serial,
+    Rtt,
+}
+
+const PORTS: [Port; 2] = [Port::Serial, Port::Rtt];
+
+/// A shell for each port, as each has its own line being typed.
+pub struct Shells {
+    serial: Shell<Console, LINE_LEN>,
+    rtt: Shell<Console, LINE_LEN>,
+}
+
+impl Shells {
+    pub fn new() -> Self {
+        Self {
+            serial: Shell::new(&COMMANDS),
+            rtt: Shell::new(&COMMANDS),
+        }
+    }
+
+    /// Prompt on every port.
+    pub fn prompt(&mut self, console: &mut Console) -> fmt::Result {
+        console.replying_to = Port::Serial;
+        self.serial.prompt(console)?;
+        console.replying_to = Port::Rtt;
+        let result = self.rtt.prompt(console);
+        console.replying_to = Port::Serial;
+        result
+    }
+}
+
+pub struct Console {
+    uart: serial::Tx,
+    received: RxConsumer,
+    demux: Demux,
+    rtt_commands: DownChannel,
+    rtt_replies: UpChannel,
+    /// The port that writes go to.
+    replying_to: Port,
+    temp: Temp,
+    reset_reason: ResetReason,
+    buttons: [bool; 2],
+    /// Whether each port asked for button events, by `Port`.
+    watching_buttons: [bool; 2],
+    /// None until the motion sensor has been read, if there is one.
+    motion: Option<Reading>,
+}
+
+impl Console {
+    /// Write through the UART, and read what `SerialRx` receives for it.
+    /// Also take commands from an RTT down channel, replying on an up
+    /// channel.
+    pub fn new(
+        uart: serial::Tx,
+        received: RxConsumer,
+        rtt_commands: DownChannel,
+        rtt_replies: UpChannel,
+        temp: Temp,
+        reset_reason: ResetReason,
+    ) -> Self {
+        Self {
+            uart,
+            received,
+            demux: Demux::new(),
+            rtt_commands,
+            rtt_replies,
+            replying_to: Port::Serial,
+            temp,
+            reset_reason,
+            buttons: [false; 2],
+            watching_buttons: [false; 2],
+            motion: None,
+        }
+    }
+
+    /// Handle everything received so far, without waiting for more.
+    pub fn poll(&mut self, shells: &mut Shells) {
+        self.poll_serial(&mut shells.serial);
+        self.poll_rtt(&mut shells.rtt);
+    }
+
+    fn poll_serial(&mut self, shell: &mut Shell<Console, LINE_LEN>) {
+        while let Some(byte) = self.received.dequeue() {
+            match self.demux.feed(byte) {
+                Some(Received::Text(byte)) => {
+                    let _ = shell.feed(self, byte);
+                }
+                Some(Received::Frame(Ok(payload))) => {
+                    let response = answer(payload, self.buttons, &mut self.temp);
+                    let mut buffer = [0; MAX_MESSAGE_LEN];
+                    match response.encode(&mut buffer) {
+                        Ok(len) => {
+                            if send_frame(&mut self.uart, &buffer[..len]).is_err() {
+                                error!("Could not send response {}", response.seq);
+                            }
+                        }
+                        Err(error) => error!("Could not encode response: {:?}", error),
+                    }
+                }
+                Some(Received::Frame(Err(error))) => warn!("Bad frame: {:?}", error),
+                None => (),
+            }
+        }
+    }
+
+    fn poll_rtt(&mut self, shell: &mut Shell<Console, LINE_LEN>) {
+        let mut bytes = [0; 16];
+        self.replying_to = Port::Rtt;
+        loop {
+            let len = self.rtt_commands.read(&mut bytes);
+            if len == 0 {
+                break;
+            }
+            for byte in bytes[..len].iter() {
+                let _ = shell.feed(self, *byte);
+            }
+        }
+        self.replying_to = Port::Serial;
+    }
+
+    /// The number of received bytes dropped because the main loop fell
+    /// behind.
+    pub fn dropped(&self) -> u32 {
+        self.received.dropped()
+    }
+
+    /// Record the debounced button states for the `button` command.
+    pub fn set_buttons(&mut self, a: bool, b: bool) {
+        self.buttons = [a, b];
+    }
+
+    /// Record what the motion sensor last measured for the `motion`
+    /// command.
+    pub fn set_motion(&mut self, reading: Reading) {
+        self.motion = Some(reading);
+    }
+
+    /// Pass on a button event to each port that asked for them with
+    /// `button watch on`.
+    pub fn notify_button(&mut self, event: PairEvent) {
+        for port in PORTS.iter() {
+            if self.watching_buttons[*port as usize] {
+                self.replying_to = *port;
+                let _ = write!(self, "button: {:?}\r\n", event);
+            }
+        }
+        self.replying_to = Port::Serial;
+    }
+}
+
+impl Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.replying_to {
+            Port::Serial => self.uart.write_str(s),
+            Port::Rtt => self.rtt_replies.write_str(s),
+        }
+    }
+}
+
+/// Carry out a request, or say why not.
+fn answer(bytes: &[u8], buttons: [bool; 2], temp: &mut Temp) -> Message<Response> {
+    let request = match Message::<Request>::decode(bytes) {
+        Ok(request) => request,
+        Err(error) => {
+            warn!("Bad request: {:?}", error);
+            return Message {
+                seq: message::sequence(bytes).unwrap_or(0),
+                body: Response::Rejected(Rejection::from(error)),
+            };
+        }
+    };
+    trace!("Request {}: {:?}", request.seq, request.body);
+    let body = match request.body {
+        Request::Ping => Response::Pong,
+        Request::GetVersion => match version_numbers() {
+            Some((major, minor, patch)) => Response::Version {
+                major,
+                minor,
+                patch,
+            },
+            None => Response::Rejected(Rejection::InvalidArgument),
+        },
+        Request::GetButtons => Response::Buttons {
+            a: buttons[ButtonId::A as usize],
+            b: buttons[ButtonId::B as usize],
+        },
+        // Measured in quarters of a degree
+        Request::GetTemperature => Response::Temperature(temp.measure().to_bits() * 25),
+        Request::SetPixel { row, col, level } => {
+            with_display(|display| display.set_pixel(usize::from(row), usize::from(col), level));
+            Response::Done
+        }
+        Request::SetImage(image) => {
+            with_display(|display| display.set_image(image));
+            Response::Done
+        }
+        Request::ScrollText(text) => {
+            with_display(|display| display.scroll(text, SCROLL_COLUMN_MS));
+            Response::Done
+        }
+    };
+    Message {
+        seq: request.seq,
+        body,
+    }
+}
+
+/// The firmware's version, as numbers.
+fn version_numbers() -> Option<(u8, u8, u8)> {
+    Some((
+        env!("CARGO_PKG_VERSION_MAJOR").parse().ok()?,
+        env!("CARGO_PKG_VERSION_MINOR").parse().ok()?,
+        env!("CARGO_PKG_VERSION_PATCH").parse().ok()?,
+    ))
+}
+
+/// Send a payload to the host as a frame.
+fn send_frame(uart: &mut serial::Tx, payload: &[u8]) -> fmt::Result {
+    let mut buffer = [0; MAX_FRAME_LEN];
+    let len = frame::encode(payload, &mut buffer).map_err(|_| fmt::Error)?;
+    serial::write_bytes(uart, &buffer[..len])
+}
+
+pub static COMMANDS: [Command<Console>; 7] = [
+    Command {
+        name: "version",
+        usage: "",
+        help: "Show the firmware version and what it was built from",
+        run: version,
+    },
+    Command {
+        name: "led",
+        usage: "brightness <0-255> | clear | fill <0-9> | image <5 rows of 0-9>",
+        help: "Control the LED matrix",
+        run: led,
+    },
+    Command {
+        name: "button",
+        usage: "[watch on|off]",
+        help: "Show whether buttons A and B are pressed, or report their events",
+        run: button,
+    },
+    Command {
+        name: "motion",
+        usage: "",
+        help: "Show the latest acceleration and magnetic field",
+        run: motion,
+    },
+    Command {
+        name: "log",
+        usage: "[off|error|warn|info|debug|trace]",
+        help: "Show or set the least important level of log record to print",
+        run: log,
+    },
+    Command {
+        name: "boot",
+        usage: "",
+        help: "Show why the board last started",
+        run: boot,
+    },
+    Command {
+        name: "reset",
+        usage: "",
+        help: "Restart the board",
+        run: reset,
+    },
+];
+
+fn version(console: &mut Console, args: Args) -> Result<(), Error> {
+    args.end()?;
+    write!(console, "{} {}\r\n", env!("CARGO_PKG_NAME"), BUILD_INFO)?;
+    Ok(())
+}
+
+fn led(_: &mut Console, mut args: Args) -> Result<(), Error> {
+    match args.required()? {
+        "brightness" => {
+            let brightness = args.parse()?;
+            args.end()?;
+            with_display(|display| display.set_brightness(brightness));
+        }
+        "clear" => {
+            args.end()?;
+            with_display(|display| display.set_image(Image::blank()));
+        }
+        "fill" => {
+            let level: u8 = args.parse()?;
+            args.end()?;
+            if level > MAX_LEVEL {
+                return Err(Error::InvalidArgument);
+            }
+            let image = Image::new([[level; COLS]; ROWS]);
+            with_display(|display| display.set_image(image));
+        }
+        "image" => {
+            let image = Image::from_rows(args).ok_or(Error::InvalidArgument)?;
+            with_display(|display| display.set_image(image));
+        }
+        _ => return Err(Error::InvalidArgument),
+    }
+    Ok(())
+}
+
+fn button(console: &mut Console, mut args: Args) -> Result<(), Error> {
+    if let Some(watch) = args.next() {
+        if watch != "watch" {
+            return Err(Error::InvalidArgument);
+        }
+        console.watching_buttons[console.replying_to as usize] = match args.required()? {
+            "on" => true,
+            "off" => false,
+            _ => return Err(Error::InvalidArgument),
+        };
+        return args.end();
+    }
+    for id in [ButtonId::A, ButtonId::B].iter() {
+        let state = if console.buttons[*id as usize] {
+            "pressed"
+        } else {
+            "released"
+        };
+        write!(console, "{:?}: {}\r\n", id, state)?;
+    }
+    Ok(())
+}
+
+fn motion(console: &mut Console, args: Args) -> Result<(), Error> {
+    args.end()?;
+    match console.motion {
+        Some(reading) => {
+            write!(console, "acceleration: {} mg\r\n", reading.acceleration)?;
+            write!(console, "magnetic field: {} nT\r\n", reading.magnetic_field)?;
+        }
+        None => console.write_str("No reading from the motion sensor\r\n")?,
+    }
+    Ok(())
+}
+
+fn log(console: &mut Console, mut args: Args) -> Result<(), Error> {
+    if let Some(name) = args.next() {
+        args.end()?;
+        let level = match name {
+            "off" => None,
+            _ => Some(name.parse().map_err(|_| Error::InvalidArgument)?),
+        };
+        MAX_LOG_LEVEL.set(level);
+        return Ok(());
+    }
+    let name = MAX_LOG_LEVEL.get().map_or("off", Level::name);
+    write!(console, "{}\r\n", name)?;
+    Ok(())
+}
+
+fn boot(console: &mut Console, args: Args) -> Result<(), Error> {
+    args.end()?;
+    let reason = console.reset_reason;
+    write!(console, "reset reason: {}\r\n", reason)?;
+    Ok(())
+}
+
+fn reset(console: &mut Console, args: Args) -> Result<(), Error> {
+    args.end()?;
+    console.write_str("Resetting\r\n")?;
+    SCB::sys_reset()
+}
@@ -0,0 +1,39 @@
+//! Keeps the last panic in RAM that startup doesn't clear, so that a
+//! release build can say why it reset once it is running again.
+//!
+//! Debug builds leave panics to `panic_probe`, which reports them to the
+//! attached debugger instead.
+
+use app::crash::{Crash, CrashRecord};
+use core::mem::MaybeUninit;
+use core::ptr;
+
+#[link_section = ".noinit.CRASH"]
+static mut CRASH: MaybeUninit<CrashRecord> = MaybeUninit::uninit();
+
+fn record() -> &'static mut CrashRecord {
+    // Safe as every bit pattern is a record, if not a valid one, and only
+    // startup and the panic handler touch it, neither of which is ever
+    // interrupted by the other
+    unsafe { &mut *(ptr::addr_of_mut!(CRASH) as *mut CrashRecord) }
+}
+
+/// Hand any crash kept from before the last reset to `f`, then forget it.
+pub fn take_last(f: impl FnOnce(&Crash)) {
+    let record = record();
+    if let Some(crash) = record.crash() {
+        f(&crash);
+    }
+    record.clear();
+}
+
+#[cfg(not(debug_assertions))]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    let (file, line, column) = info.location().map_or(("", 0, 0), |location| {
+        (location.file(), location.line(), location.column())
+    });
+    record().record(file, line, column, format_args!("{}", info.message()));
+    hal::pac::SCB::sys_reset()
+}
@@ -0,0 +1,162 @@
+//! Drives the LED matrix from a timer interrupt. Each row is lit for a fixed
+//! period, with extra interrupts within the period to switch off dimmer
+//! pixels early.
+
+use crate::board::{LedPin, LAYOUT, MATRIX_COLS, MATRIX_ROWS};
+use app::{
+    animation::{Animation, Player},
+    display::{Image, Scanner, MAX_BRIGHTNESS},
+    message::MAX_TEXT_LEN,
+    scroll::{Scroller, Text},
+    transform::Rotation,
+    transition::{Effect, Transition},
+};
+use hal::{
+    prelude::*,
+    timer::{self, OneShot, Timer},
+};
+
+/// How many times per second the whole matrix is redrawn.
+pub const FRAME_RATE_HZ: u32 = 100;
+
+const ROW_PERIOD_US: u32 = LAYOUT.row_period_us(FRAME_RATE_HZ);
+const FRAME_PERIOD_MS: u32 = 1_000 / FRAME_RATE_HZ;
+
+/// What moves the frame buffer on from one frame to the next.
+enum Content {
+    Still,
+    Scroll(Scroller<Text<MAX_TEXT_LEN>>),
+    Animation(Player<'static>),
+    Transition(Transition),
+}
+
+pub struct Display<T: timer::Instance> {
+    timer: Timer<T, OneShot>,
+    rows: [LedPin; MATRIX_ROWS],
+    cols: [LedPin; MATRIX_COLS],
+    scanner: Scanner<MATRIX_ROWS, MATRIX_COLS>,
+    image: Image,
+    frame: Image,
+    rotation: Rotation,
+    brightness: u8,
+    content: Content,
+}
+
+impl<T: timer::Instance> Display<T> {
+    /// Take ownership of the matrix pins and start refreshing. The timer's
+    /// interrupt must be unmasked and forwarded to `handle_interrupt`.
+    pub fn new(timer: T, rows: [LedPin; MATRIX_ROWS], cols: [LedPin; MATRIX_COLS]) -> Self {
+        let mut timer = Timer::one_shot(timer);
+        timer.enable_interrupt();
+        timer.start(ROW_PERIOD_US);
+        Self {
+            timer,
+            rows,
+            cols,
+            scanner: Scanner::with_layout(&LAYOUT),
+            image: Image::blank(),
+            frame: Image::blank(),
+            rotation: Rotation::None,
+            brightness: MAX_BRIGHTNESS,
+            content: Content::Still,
+        }
+    }
+
+    /// Replace the frame buffer, stopping any scrolling text or animation.
+    /// The new image is picked up from the next frame.
+    pub fn set_image(&mut self, image: Image) {
+        self.content = Content::Still;
+        self.image = image;
+    }
+
+    /// Change one pixel of the frame buffer, stopping any scrolling text or
+    /// animation.
+    pub fn set_pixel(&mut self, row: usize, col: usize, level: u8) {
+        self.content = Content::Still;
+        self.image.set_pixel(row, col, level);
+    }
+
+    /// Scroll text across the display, moving one column every `column_ms`.
+    /// Only the first `MAX_TEXT_LEN` bytes are kept. The display is left
+    /// blank once the text has gone.
+    pub fn scroll(&mut self, text: &str, column_ms: u32) {
+        self.content = Content::Scroll(Scroller::new(Text::new(text), column_ms));
+    }
+
+    /// Play an animation from its first frame. A `Playback::Once` animation
+    /// is left showing its last frame.
+    pub fn play(&mut self, animation: Animation<'static>) {
+        let player = Player::new(animation);
+        self.image = player.image();
+        self.content = Content::Animation(player);
+    }
+
+    /// Move from the current image to a new one over `duration_ms`, taking
+    /// one step of the effect per frame.
+    pub fn transition_to(&mut self, image: Image, effect: Effect, duration_ms: u32) {
+        let steps = (duration_ms / FRAME_PERIOD_MS) as usize;
+        if steps == 0 {
+            self.set_image(image);
+        } else {
+            self.content = Content::Transition(Transition::new(self.image, image, effect, steps));
+        }
+    }
+
+    /// Turn every image to suit the way the board is mounted.
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    /// Scale every pixel's level, from 0 (off) to `MAX_BRIGHTNESS`.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+    }
+
+    pub fn handle_interrupt(&mut self) {
+        // Clears the compare event
+        let _ = self.timer.wait();
+
+        if self.scanner.is_frame_start() {
+            self.advance_frame();
+            self.frame = self.image.rotated(self.rotation);
+        }
+
+        let drive = self
+            .scanner
+            .step(&self.frame, self.brightness, ROW_PERIOD_US);
+
+        // Turn the other rows off before moving the columns to avoid ghosting
+        for (row_led, level) in self.rows.iter_mut().zip(drive.rows.iter()) {
+            if !*level {
+                let _ = row_led.set_low();
+            }
+        }
+        for (col_led, level) in self.cols.iter_mut().zip(drive.cols.iter()) {
+            let _ = if *level {
+                col_led.set_high()
+            } else {
+                col_led.set_low()
+            };
+        }
+        let _ = self.rows[drive.row].set_high();
+
+        self.timer.start(drive.next_us);
+    }
+
+    fn advance_frame(&mut self) {
+        let (image, finished) = match &mut self.content {
+            Content::Still => return,
+            Content::Scroll(scroller) => {
+                (scroller.advance(FRAME_PERIOD_MS), scroller.is_finished())
+            }
+            Content::Animation(player) => (player.advance(FRAME_PERIOD_MS), player.is_finished()),
+            Content::Transition(transition) => (transition.next(), transition.is_finished()),
+        };
+        if let Some(image) = image {
+            self.image = image;
+        }
+        if finished {
+            self.content = Content::Still;
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Catches a HardFault, such as from a bad memory access, reporting what the
+//! processor knows about it over RTT and keeping that for the next boot
+//! before resetting.
+
+use app::fault::{ExceptionFrame, Fault, FaultRecord, FaultStatus, STACK_WORDS};
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+use cortex_m_rt::exception;
+#[cfg(feature = "v2")]
+use hal::pac::FPU;
+use hal::pac::SCB;
+
+#[link_section = ".noinit.FAULT"]
+static mut FAULT: MaybeUninit<FaultRecord> = MaybeUninit::uninit();
+
+extern "C" {
+    // The top of the stack, from the linker script
+    static _stack_start: u32;
+}
+
+fn record() -> &'static mut FaultRecord {
+    // Safe as every bit pattern is a record, if not a valid one, and only
+    // startup and the HardFault handler touch it, neither of which is ever
+    // interrupted by the other
+    unsafe { &mut *(ptr::addr_of_mut!(FAULT) as *mut FaultRecord) }
+}
+
+/// Hand any fault kept from before the last reset to `f`, then forget it.
+pub fn take_last(f: impl FnOnce(&Fault)) {
+    let record = record();
+    if let Some(fault) = record.fault() {
+        f(&fault);
+    }
+    record.clear();
+}
+
+/// What the fault status registers say. The v1's Cortex-M0 has none, so
+/// its faults are only known by their stacked registers.
+#[cfg(feature = "v2")]
+fn status() -> FaultStatus {
+    // Safe as the status registers are only read
+    let scb = unsafe { &*SCB::ptr() };
+    FaultStatus {
+        cfsr: scb.cfsr.read(),
+        hfsr: scb.hfsr.read(),
+        mmfar: scb.mmfar.read(),
+        bfar: scb.bfar.read(),
+    }
+}
+
+#[cfg(feature = "v1")]
+fn status() -> FaultStatus {
+    FaultStatus::default()
+}
+
+/// Whether room for the FPU's registers was stacked with the exception
+/// frame, as the context had used the FPU. With lazy stacking, on from
+/// reset, FPCCR.LSPACT stays set until the handler first uses the FPU
+/// itself. The v1's Cortex-M0 has no FPU.
+#[cfg(feature = "v2")]
+fn fp_registers_stacked() -> bool {
+    const LSPACT: u32 = 1 << 0;
+    // Safe as FPCCR is only read
+    let fpu = unsafe { &*FPU::PTR };
+    fpu.fpccr.read() & LSPACT != 0
+}
+
+#[cfg(feature = "v1")]
+fn fp_registers_stacked() -> bool {
+    false
+}
+
+#[exception]
+fn HardFault(frame: &cortex_m_rt::ExceptionFrame) -> ! {
+    // First, as using the FPU here would stack its registers and clear the
+    // flag saying room was made for them
+    let fp_registers = fp_registers_stacked();
+
+    let mut fault = Fault {
+        frame: ExceptionFrame {
+            r0: frame.r0,
+            r1: frame.r1,
+            r2: frame.r2,
+            r3: frame.r3,
+            r12: frame.r12,
+            lr: frame.lr,
+            pc: frame.pc,
+            xpsr: frame.xpsr,
+        },
+        fp_registers,
+        status: status(),
+        ..Fault::default()
+    };
+
+    // Keep what was on the stack before the fault, stopping at its top.
+    // The FPU's registers and a word of padding may sit between the two.
+    let above = (frame as *const cortex_m_rt::ExceptionFrame as *const u32)
+        .wrapping_add(fault.frame.stacked_words(fp_registers));
+    let top = ptr::addr_of!(_stack_start) as usize;
+    let available = top.saturating_sub(above as usize) / mem::size_of::<u32>();
+    fault.stack_len = available.min(STACK_WORDS);
+    for (i, word) in fault.stack[..fault.stack_len].iter_mut().enumerate() {
+        // Safe as the words are between the frame and the top of the stack
+        *word = unsafe { ptr::read_volatile(above.add(i)) };
+    }
+
+    fault.report(|line| error!("{}", line));
+    record().record(&fault);
+    SCB::sys_reset()
+}
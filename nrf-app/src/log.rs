@@ -0,0 +1,61 @@
+//! Log records go out over RTT, stamped with the time from `clock`, for
+//! those at or above the maximum level. The level can be changed at any
+//! time, such as from the `log` shell command.
+
+use crate::clock;
+use app::log::{self, Level, MaxLevel, Record};
+use core::fmt;
+use rtt_target::rprintln;
+
+const DEFAULT_LEVEL: Level = if cfg!(debug_assertions) {
+    Level::Debug
+} else {
+    Level::Info
+};
+
+pub static MAX_LEVEL: MaxLevel = MaxLevel::new(Some(DEFAULT_LEVEL));
+
+/// Use the `error!` to `trace!` macros rather than calling this directly.
+pub fn log(level: Level, module_path: &str, args: fmt::Arguments) {
+    if MAX_LEVEL.enabled(level) {
+        rprintln!(
+            "{}",
+            Record {
+                at_ms: clock::now_ms(),
+                level,
+                tag: log::tag(module_path),
+                args,
+            }
+        );
+    }
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::log::log(app::log::Level::Error, module_path!(), format_args!($($arg)*))
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        $crate::log::log(app::log::Level::Warn, module_path!(), format_args!($($arg)*))
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log::log(app::log::Level::Info, module_path!(), format_args!($($arg)*))
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::log::log(app::log::Level::Debug, module_path!(), format_args!($($arg)*))
+    };
+}
+
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        $crate::log::log(app::log::Level::Trace, module_path!(), format_args!($($arg)*))
+    };
+}
@@ -1,135 +1,373 @@
 #![no_std]
 #![no_main]
 
+#[cfg(feature = "v1")]
+extern crate nrf51_hal as hal;
+#[cfg(feature = "v2")]
 extern crate nrf52833_hal as hal;
 
+#[macro_use]
+mod log;
+
+mod board;
+mod build_info;
+mod buttons;
+mod clock;
+mod console;
+mod crash;
+mod display;
+mod fault;
+mod motion;
+mod serial;
+mod watchdog;
+
+use app::{
+    animation,
+    button::{self, ButtonId, Pair, PairEvent},
+    display::Image,
+    queue::Queue,
+    reset::ResetReason,
+    transform::Rotation,
+    transition::{Direction, Effect},
+    watchdog::Supervisor,
+};
 use core::{cell::RefCell, fmt::Write};
 use cortex_m::{asm, interrupt::Mutex};
 use cortex_m_rt::entry;
-use hal::{pac::interrupt, prelude::*};
+use hal::pac::interrupt;
 #[cfg(debug_assertions)]
 use panic_probe as _;
-#[cfg(not(debug_assertions))]
-use panic_reset as _;
-use rtt_target::{rprintln, rtt_init_print};
+use rtt_target::{rtt_init, set_print_channel};
+
+use board::Board;
+use build_info::BUILD_INFO;
+use buttons::{ButtonEdges, ButtonSample, SAMPLE_QUEUE_LEN};
+use clock::Clock;
+use console::{Console, Shells};
+use display::Display;
+use serial::{SerialRx, RX_QUEUE_LEN};
+use watchdog::{TaskId, TaskSupervisor, TASKS};
 
-static GPIOTE: Mutex<RefCell<Option<hal::gpiote::Gpiote>>> = Mutex::new(RefCell::new(None));
+/// Full brightness is harsh on the eyes when sitting in front of the board.
+const DISPLAY_BRIGHTNESS: u8 = 192;
+const SCROLL_COLUMN_MS: u32 = 80;
+const TRANSITION_MS: u32 = 300;
+/// How close together presses of A and B must be to count as both at once.
+const CHORD_MS: u32 = 100;
+/// Change this when the board is mounted with the USB connector other than
+/// at the top.
+const DISPLAY_ROTATION: Rotation = Rotation::None;
+
+static BUTTON_SAMPLES: Queue<ButtonSample, SAMPLE_QUEUE_LEN> = Queue::new();
+static BUTTON_EDGES: Mutex<RefCell<Option<ButtonEdges>>> = Mutex::new(RefCell::new(None));
+static CLOCK: Mutex<RefCell<Option<Clock>>> = Mutex::new(RefCell::new(None));
+static SERIAL_BYTES: Queue<u8, RX_QUEUE_LEN> = Queue::new();
+static SERIAL_RX: Mutex<RefCell<Option<SerialRx>>> = Mutex::new(RefCell::new(None));
+static DISPLAY: Mutex<RefCell<Option<Display<hal::pac::TIMER1>>>> = Mutex::new(RefCell::new(None));
+static SUPERVISOR: TaskSupervisor = Supervisor::new(TASKS);
 
 #[interrupt]
 fn GPIOTE() {
     cortex_m::interrupt::free(|cs| {
-        let gpiote = GPIOTE.borrow(cs).borrow();
-        if let Some(gpiote) = gpiote.as_ref() {
-            gpiote.reset_events();
+        let mut button_edges = BUTTON_EDGES.borrow(cs).borrow_mut();
+        if let Some(button_edges) = button_edges.as_mut() {
+            button_edges.handle_interrupt();
+        }
+    });
+}
+
+#[interrupt]
+fn RTC1() {
+    cortex_m::interrupt::free(|cs| {
+        let mut clock = CLOCK.borrow(cs).borrow_mut();
+        if let Some(clock) = clock.as_mut() {
+            clock.handle_interrupt();
+        }
+    });
+}
+
+#[interrupt]
+fn TIMER1() {
+    cortex_m::interrupt::free(|cs| {
+        let mut display = DISPLAY.borrow(cs).borrow_mut();
+        if let Some(display) = display.as_mut() {
+            display.handle_interrupt();
+        }
+    });
+    SUPERVISOR.check_in(TaskId::Display as usize, clock::now_ms());
+}
+
+/// Takes no critical section of its own, as the reset follows so closely.
+#[interrupt]
+fn WDT() {
+    watchdog::handle_timeout(SUPERVISOR.check(clock::now_ms()).err());
+}
+
+#[cfg(feature = "v1")]
+#[interrupt]
+fn UART0() {
+    handle_serial_rx();
+}
+
+#[cfg(feature = "v2")]
+#[interrupt]
+fn UARTE0_UART0() {
+    handle_serial_rx();
+}
+
+fn handle_serial_rx() {
+    cortex_m::interrupt::free(|cs| {
+        let mut serial_rx = SERIAL_RX.borrow(cs).borrow_mut();
+        if let Some(serial_rx) = serial_rx.as_mut() {
+            serial_rx.handle_interrupt();
+        }
+    });
+}
+
+/// The NVIC priority for a level, 0 being the most urgent, in the bits that
+/// the chip implements: the top three on the nRF52, but only the top two on
+/// the nRF51, which so has just four levels.
+const fn priority(level: u8) -> u8 {
+    level << (8 - hal::pac::NVIC_PRIO_BITS)
+}
+
+fn with_display<F: FnOnce(&mut Display<hal::pac::TIMER1>)>(f: F) {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(display) = DISPLAY.borrow(cs).borrow_mut().as_mut() {
+            f(display);
         }
     });
 }
 
 #[entry]
 fn main() -> ! {
+    // The log and command replies go up separate channels so they can be
+    // shown apart, and commands come down the first down channel
+    let rtt = rtt_init! {
+        up: {
+            0: {
+                size: 1024
+                mode: NoBlockSkip
+                name: "Terminal"
+            }
+            1: {
+                size: 512
+                mode: NoBlockTrim
+                name: "Replies"
+            }
+        }
+        down: {
+            0: {
+                size: 64
+                name: "Commands"
+            }
+        }
+    };
+    set_print_channel(rtt.up.0);
+
     let mut cp = hal::pac::CorePeripherals::take().unwrap();
-    let p = hal::pac::Peripherals::take().unwrap();
-
-    let p0 = hal::gpio::p0::Parts::new(p.P0);
-    let p1 = hal::gpio::p1::Parts::new(p.P1);
-
-    let cdc_pins = hal::uarte::Pins {
-        txd: p0
-            .p0_06
-            .into_push_pull_output(hal::gpio::Level::High)
-            .degrade(),
-        rxd: p0.p0_08.into_floating_input().degrade(),
-        cts: None,
-        rts: None,
+    let board = Board::new(hal::pac::Peripherals::take().unwrap());
+
+    // Each reset adds its bit to those already set, so clear them once read
+    let reset_reason = ResetReason::from_bits(board.POWER.resetreas.read().bits());
+    board
+        .POWER
+        .resetreas
+        .write(|w| unsafe { w.bits(0xffff_ffff) });
+
+    let _clocks = hal::clocks::Clocks::new(board.CLOCK).start_lfclk();
+    let clock = Clock::new(board.RTC1);
+    cortex_m::interrupt::free(|cs| {
+        CLOCK.borrow(cs).replace(Some(clock));
+    });
+
+    #[cfg(feature = "v1")]
+    let mut uart = serial::open(board.UART0, board.uart);
+    #[cfg(feature = "v2")]
+    let mut uart = serial::open(board.UARTE0, board.uart);
+
+    write!(uart, "Hello, World! Reset reason: {}\r\n", reset_reason).unwrap();
+    write!(uart, "{} {}\r\n", env!("CARGO_PKG_NAME"), BUILD_INFO).unwrap();
+    info!("{} {}", env!("CARGO_PKG_NAME"), BUILD_INFO);
+    info!("Reset reason: {}", reset_reason);
+    crash::take_last(|crash| {
+        error!("Reset after a crash: {}", crash);
+        write!(uart, "Reset after a crash: {}\r\n", crash).unwrap();
+    });
+    watchdog::take_last(|missed| {
+        error!("Reset by the watchdog: {}", missed);
+        write!(uart, "Reset by the watchdog: {}\r\n", missed).unwrap();
+    });
+    fault::take_last(|fault| {
+        fault.report(|line| {
+            error!("Reset after a fault: {}", line);
+            write!(uart, "Reset after a fault: {}\r\n", line).unwrap();
+        })
+    });
+
+    #[cfg(feature = "v1")]
+    let motion = motion::open(board.TWI0, board.i2c);
+    #[cfg(feature = "v2")]
+    let motion = motion::open(board.TWIM0, board.internal_i2c);
+    let mut motion = match motion {
+        Ok(motion) => Some(motion),
+        Err(error) => {
+            warn!("No motion sensor: {}", error);
+            None
+        }
     };
 
-    let mut uarte = hal::uarte::Uarte::new(
-        p.UARTE0,
-        cdc_pins,
-        hal::uarte::Parity::EXCLUDED,
-        hal::uarte::Baudrate::BAUD115200,
-    );
-
-    write!(uarte, "Hello, World!\r\n").unwrap();
-
-    let mut row_leds = [
-        p0.p0_21
-            .into_push_pull_output(hal::gpio::Level::Low)
-            .degrade(),
-        p0.p0_22
-            .into_push_pull_output(hal::gpio::Level::Low)
-            .degrade(),
-        p0.p0_15
-            .into_push_pull_output(hal::gpio::Level::Low)
-            .degrade(),
-        p0.p0_24
-            .into_push_pull_output(hal::gpio::Level::Low)
-            .degrade(),
-        p0.p0_19
-            .into_push_pull_output(hal::gpio::Level::Low)
-            .degrade(),
-    ];
-
-    let mut col_leds = [
-        p0.p0_28
-            .into_push_pull_output(hal::gpio::Level::High)
-            .degrade(),
-        p0.p0_11
-            .into_push_pull_output(hal::gpio::Level::High)
-            .degrade(),
-        p0.p0_31
-            .into_push_pull_output(hal::gpio::Level::High)
-            .degrade(),
-        p1.p1_05
-            .into_push_pull_output(hal::gpio::Level::High)
-            .degrade(),
-        p0.p0_30
-            .into_push_pull_output(hal::gpio::Level::High)
-            .degrade(),
-    ];
+    let (row_leds, col_leds) = board.display.degrade();
+    let mut display = Display::new(board.TIMER1, row_leds, col_leds);
+    display.set_rotation(DISPLAY_ROTATION);
+    display.set_brightness(DISPLAY_BRIGHTNESS);
+    display.scroll("Hello, World!", SCROLL_COLUMN_MS);
+    cortex_m::interrupt::free(|cs| {
+        DISPLAY.borrow(cs).replace(Some(display));
+    });
 
     unsafe {
         hal::pac::NVIC::unmask(hal::pac::Interrupt::GPIOTE);
-        cp.NVIC.set_priority(hal::pac::Interrupt::GPIOTE, 32 << 5);
+        cp.NVIC
+            .set_priority(hal::pac::Interrupt::GPIOTE, priority(3));
+        hal::pac::NVIC::unmask(hal::pac::Interrupt::TIMER1);
+        cp.NVIC
+            .set_priority(hal::pac::Interrupt::TIMER1, priority(1));
+        hal::pac::NVIC::unmask(hal::pac::Interrupt::RTC1);
+        cp.NVIC.set_priority(hal::pac::Interrupt::RTC1, priority(2));
+        hal::pac::NVIC::unmask(serial::INTERRUPT);
+        cp.NVIC.set_priority(serial::INTERRUPT, priority(3));
+        // Above all else, as the reset follows its timeout so closely. Only
+        // the critical sections of the other handlers and the main loop can
+        // hold it off, so they must be kept short.
+        hal::pac::NVIC::unmask(hal::pac::Interrupt::WDT);
+        cp.NVIC.set_priority(hal::pac::Interrupt::WDT, priority(0));
     }
 
-    let button_a = p0.p0_14.into_floating_input().degrade();
-    let gpiote = hal::gpiote::Gpiote::new(p.GPIOTE);
-    gpiote
-        .channel0()
-        .input_pin(&button_a)
-        .hi_to_lo()
-        .enable_interrupt();
+    let button_a = board.buttons.a.degrade();
+    let button_b = board.buttons.b.degrade();
+    let (sample_producer, mut sample_consumer) = BUTTON_SAMPLES.split().unwrap();
+    let button_edges = ButtonEdges::new(board.GPIOTE, button_a, button_b, sample_producer);
     cortex_m::interrupt::free(|cs| {
-        GPIOTE.borrow(cs).replace(Some(gpiote));
+        BUTTON_EDGES.borrow(cs).replace(Some(button_edges));
     });
 
-    rtt_init_print!();
+    let graphic = Image::new([
+        [9, 7, 5, 0, 0],
+        [0, 9, 0, 3, 1],
+        [0, 9, 7, 0, 0],
+        [0, 9, 7, 0, 0],
+        [0, 0, 0, 9, 9],
+    ]);
+
+    let (rx_producer, rx_consumer) = SERIAL_BYTES.split().unwrap();
+    cortex_m::interrupt::free(|cs| {
+        let mut serial_rx = SERIAL_RX.borrow(cs).borrow_mut();
+        serial_rx.replace(SerialRx::new(rx_producer));
+        // Only started once in place, as the v2's hardware is given its
+        // buffers
+        if let Some(serial_rx) = serial_rx.as_mut() {
+            serial_rx.start();
+        }
+    });
+    let temp = hal::temp::Temp::new(board.TEMP);
+    let mut console = Console::new(uart, rx_consumer, rtt.down.0, rtt.up.1, temp, reset_reason);
+    let mut shells = Shells::new();
+    shells.prompt(&mut console).unwrap();
 
-    let graphic = [
-        [1, 1, 1, 0, 0],
-        [0, 1, 0, 1, 1],
-        [0, 1, 1, 0, 0],
-        [0, 1, 1, 0, 0],
-        [0, 0, 0, 1, 1],
-    ];
+    let mut buttons = Pair::new(button::Timings::default(), CHORD_MS);
+    info!("Wait for event.");
+    let mut raw_pressed = [false; 2];
+    let mut reported_dropped = 0;
+    let mut reported_serial_lost = 0;
+    let mut reported_missed = false;
+    let mut reported_motion_error = false;
 
+    SUPERVISOR.start(clock::now_ms());
+    let mut watchdog = watchdog::start(board.WDT);
     loop {
-        let button_a_pressed = app::is_result_ok_and_true(button_a.is_low());
-        if button_a_pressed {
-            for (row, row_led) in row_leds.iter_mut().enumerate() {
-                for (col, col_led) in col_leds.iter_mut().enumerate() {
-                    if graphic[row][col] == 1 {
-                        let _ = row_led.set_high();
-                        let _ = col_led.set_low();
-                        let _ = row_led.set_low();
-                        let _ = col_led.set_high();
-                    }
+        // Samples taken on each edge come first. The display refresh then
+        // keeps waking us often enough for the buttons to settle and to
+        // time long presses from the last known states.
+        for sample in sample_consumer.by_ref() {
+            raw_pressed[sample.id as usize] = sample.pressed;
+            if let Some(event) = buttons.update(sample.at_ms, sample.id, sample.pressed) {
+                on_button_event(&mut console, &graphic, event);
+            }
+        }
+        let now_ms = clock::now_ms();
+        for id in [ButtonId::A, ButtonId::B].iter() {
+            if let Some(event) = buttons.update(now_ms, *id, raw_pressed[*id as usize]) {
+                on_button_event(&mut console, &graphic, event);
+            }
+        }
+
+        let dropped = sample_consumer.dropped();
+        if dropped != reported_dropped {
+            warn!("Button samples dropped: {}", dropped);
+            reported_dropped = dropped;
+        }
+
+        console.set_buttons(
+            buttons.is_pressed(ButtonId::A),
+            buttons.is_pressed(ButtonId::B),
+        );
+        match motion.as_mut().map(|motion| motion.poll()) {
+            Some(Ok(Some(reading))) => {
+                if reading.shaken {
+                    info!("Shaken: {} mg", reading.acceleration);
                 }
+                console.set_motion(reading);
+            }
+            Some(Err(error)) if !reported_motion_error => {
+                warn!("Reading the motion sensor: {}", error);
+                reported_motion_error = true;
+            }
+            _ => (),
+        }
+
+        console.poll(&mut shells);
+
+        let serial_lost = console.dropped() + serial::overruns();
+        if serial_lost != reported_serial_lost {
+            warn!("Serial bytes lost: {}", serial_lost);
+            reported_serial_lost = serial_lost;
+        }
+
+        // A task falling behind stops the feeding, leaving the watchdog to
+        // reset the board
+        let now_ms = clock::now_ms();
+        SUPERVISOR.check_in(TaskId::MainLoop as usize, now_ms);
+        match SUPERVISOR.check(now_ms) {
+            Ok(()) => watchdog.pet(),
+            Err(missed) if !reported_missed => {
+                error!("{}", missed);
+                reported_missed = true;
             }
-        } else {
-            rprintln!("Wait for event.");
-            asm::wfe();
+            Err(_) => (),
         }
+
+        asm::wfe();
     }
 }
+
+fn on_button_event(console: &mut Console, graphic: &Image, event: PairEvent) {
+    debug!("{:?}", event);
+    console.notify_button(event);
+    with_display(|display| match event {
+        PairEvent::Button(ButtonId::A, button::Event::Pressed) => {
+            display.transition_to(*graphic, Effect::Wipe(Direction::Left), TRANSITION_MS)
+        }
+        PairEvent::Button(ButtonId::B, button::Event::Pressed) => display.transition_to(
+            graphic.flipped_horizontally(),
+            Effect::Wipe(Direction::Right),
+            TRANSITION_MS,
+        ),
+        PairEvent::Button(_, button::Event::DoubleClick) => {
+            display.transition_to(graphic.inverted(), Effect::Dissolve, TRANSITION_MS)
+        }
+        PairEvent::Button(_, button::Event::LongPress) => display.play(animation::SPINNER),
+        PairEvent::Button(_, button::Event::Released) => display.play(animation::HEARTBEAT),
+        PairEvent::Chord => display.play(animation::ARROW_RIGHT),
+    });
+}
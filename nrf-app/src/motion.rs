@@ -0,0 +1,110 @@
+//! The LSM303AGR motion sensor, on the v2's internal I2C bus through its
+//! TWIM, and on the v1's only I2C bus through its TWI. The earliest v1s
+//! were built with other sensors, so the firmware carries on without one.
+//!
+//! The accelerometer pulls the interrupt line low with each new sample and
+//! when the board is shaken, which the main loop watches for. On the v2 the
+//! interface chip shares the line, so it may also be low for no news here.
+
+use app::lsm303agr::{self, AccelRate, AccelScale, Interrupts, Lsm303agr, MagRate, Motion, Vector};
+use embedded_hal::digital::v2::InputPin;
+use hal::gpio::{Floating, Input, Pin};
+
+#[cfg(feature = "v1")]
+use crate::board::I2cPins;
+#[cfg(feature = "v1")]
+use hal::{
+    pac::TWI0,
+    twi::{self, Frequency, Twi},
+};
+
+#[cfg(feature = "v2")]
+use crate::board::InternalI2cPins;
+#[cfg(feature = "v2")]
+use hal::{
+    pac::TWIM0,
+    twim::{self, Frequency, Twim},
+};
+
+#[cfg(feature = "v1")]
+pub type Bus = Twi<TWI0>;
+#[cfg(feature = "v1")]
+pub type Error = lsm303agr::Error<twi::Error>;
+
+#[cfg(feature = "v2")]
+pub type Bus = Twim<TWIM0>;
+#[cfg(feature = "v2")]
+pub type Error = lsm303agr::Error<twim::Error>;
+
+const ACCEL_RATE: AccelRate = AccelRate::Hz10;
+const ACCEL_SCALE: AccelScale = AccelScale::G4;
+const MAG_RATE: MagRate = MagRate::Hz10;
+/// Half as much again as gravity, for a moment, is a shake.
+const SHAKE: Motion = Motion {
+    threshold_mg: 1500,
+    duration_ms: 0,
+};
+
+/// What the sensor last measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reading {
+    /// In milli-g.
+    pub acceleration: Vector,
+    /// In nanotesla.
+    pub magnetic_field: Vector,
+    /// Whether the board was shaken since the last reading.
+    pub shaken: bool,
+}
+
+pub struct MotionSensor {
+    sensor: Lsm303agr<Bus>,
+    int: Pin<Input<Floating>>,
+}
+
+#[cfg(feature = "v1")]
+pub fn open(twi: TWI0, pins: I2cPins) -> Result<MotionSensor, Error> {
+    let int = pins.int.degrade();
+    let pins = twi::Pins {
+        scl: pins.scl.degrade(),
+        sda: pins.sda.degrade(),
+    };
+    MotionSensor::new(Twi::new(twi, pins, Frequency::K400), int)
+}
+
+#[cfg(feature = "v2")]
+pub fn open(twim: TWIM0, pins: InternalI2cPins) -> Result<MotionSensor, Error> {
+    let int = pins.int.degrade();
+    let pins = twim::Pins {
+        scl: pins.scl.degrade(),
+        sda: pins.sda.degrade(),
+    };
+    MotionSensor::new(Twim::new(twim, pins, Frequency::K400), int)
+}
+
+impl MotionSensor {
+    fn new(bus: Bus, int: Pin<Input<Floating>>) -> Result<Self, Error> {
+        let mut sensor = Lsm303agr::new(bus);
+        sensor.init()?;
+        sensor.set_accel_scale(ACCEL_SCALE)?;
+        sensor.set_accel_rate(ACCEL_RATE)?;
+        sensor.set_mag_rate(MAG_RATE)?;
+        sensor.set_interrupts(Interrupts {
+            data_ready: true,
+            motion: Some(SHAKE),
+        })?;
+        Ok(Self { sensor, int })
+    }
+
+    /// Read the sensor if the interrupt line is low. Reading lets the line
+    /// go again.
+    pub fn poll(&mut self) -> Result<Option<Reading>, Error> {
+        if !matches!(self.int.is_low(), Ok(true)) {
+            return Ok(None);
+        }
+        Ok(Some(Reading {
+            shaken: self.sensor.motion_detected()?,
+            acceleration: self.sensor.acceleration()?,
+            magnetic_field: self.sensor.magnetic_field()?,
+        }))
+    }
+}
@@ -0,0 +1,36 @@
+//! Receives from the UART in the background, posting each byte to the main
+//! loop through a queue. How depends on the version of the board, as the
+//! v1 only has a UART that receives a byte at a time, where the v2 also
+//! has a UARTE with EasyDMA.
+
+use app::queue::{Consumer, Producer};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+#[cfg(feature = "v1")]
+mod v1;
+#[cfg(feature = "v1")]
+pub use v1::*;
+
+#[cfg(feature = "v2")]
+mod v2;
+#[cfg(feature = "v2")]
+pub use v2::*;
+
+pub const RX_QUEUE_LEN: usize = 64;
+
+pub type RxProducer = Producer<'static, u8, RX_QUEUE_LEN>;
+pub type RxConsumer = Consumer<'static, u8, RX_QUEUE_LEN>;
+
+static OVERRUNS: AtomicU32 = AtomicU32::new(0);
+
+/// Only called from the UART's interrupt, so the count needs no
+/// read-modify-write, which the v1's Cortex-M0 doesn't have.
+fn count_overrun() {
+    OVERRUNS.store(OVERRUNS.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
+
+/// How many times a byte arrived before the last could be taken by the
+/// hardware.
+pub fn overruns() -> u32 {
+    OVERRUNS.load(Ordering::Relaxed)
+}
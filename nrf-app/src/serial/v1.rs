@@ -0,0 +1,78 @@
+//! The v1's UART, which transmits from the main loop a byte at a time, and
+//! receives in the background.
+//!
+//! Each byte received raises RXDRDY, and is read out from the interrupt
+//! straight into the queue. The UART only holds a few bytes, so anything
+//! keeping the interrupt waiting too long shows up as overruns.
+
+use super::{count_overrun, RxProducer};
+use crate::board::UartPins;
+use core::fmt;
+use embedded_hal::serial::Write;
+use hal::{
+    pac::{uart0, Interrupt, UART0},
+    uart::{Baudrate, Parity, Uart},
+};
+
+/// The interrupt to forward to `SerialRx::handle_interrupt`.
+pub const INTERRUPT: Interrupt = Interrupt::UART0;
+
+/// The transmitting half of the UART.
+pub type Tx = Uart<UART0>;
+
+/// Enable the UART, at 115200 baud.
+pub fn open(uart: UART0, pins: UartPins) -> Tx {
+    Uart::new(uart, pins.into(), Parity::EXCLUDED, Baudrate::BAUD115200)
+}
+
+/// Transmit `bytes`, waiting until they have all gone.
+pub fn write_bytes(tx: &mut Tx, bytes: &[u8]) -> fmt::Result {
+    for byte in bytes {
+        // Only ever waits, as transmitting can't fail
+        while tx.write(*byte).is_err() {}
+    }
+    Ok(())
+}
+
+pub struct SerialRx {
+    bytes: RxProducer,
+}
+
+impl SerialRx {
+    pub fn new(bytes: RxProducer) -> Self {
+        Self { bytes }
+    }
+
+    /// Begin receiving. The UART must already be enabled, and its interrupt
+    /// unmasked and forwarded to `handle_interrupt`.
+    pub fn start(&mut self) {
+        uart().intenset.write(|w| w.rxdrdy().set().error().set());
+    }
+
+    pub fn handle_interrupt(&mut self) {
+        let uart = uart();
+
+        if uart.events_rxdrdy.read().bits() != 0 {
+            uart.events_rxdrdy.reset();
+            let byte = uart.rxd.read().bits() as u8;
+            // A full queue counts the drop for the main loop to report
+            let _ = self.bytes.enqueue(byte);
+        }
+
+        if uart.events_error.read().bits() != 0 {
+            uart.events_error.reset();
+            let source = uart.errorsrc.read();
+            if source.overrun().is_present() {
+                count_overrun();
+            }
+            // Writing the bits back clears them
+            uart.errorsrc.write(|w| unsafe { w.bits(source.bits()) });
+        }
+    }
+}
+
+fn uart() -> &'static uart0::RegisterBlock {
+    // Safe as the HAL's handle on the UART only ever transmits, leaving
+    // the receive registers to us
+    unsafe { &*UART0::ptr() }
+}
@@ -0,0 +1,111 @@
+//! The v2's UARTE, which transmits with EasyDMA from the main loop, and
+//! receives with it in the background.
+//!
+//! EasyDMA receives into one of two single byte buffers while the other is
+//! read out from the interrupt. The ENDRX to STARTRX shortcut restarts
+//! reception as soon as a byte is in, and each RXSTARTED points the
+//! hardware at the buffer to use after the current one.
+
+use super::{count_overrun, RxProducer};
+use crate::board::UartPins;
+use core::fmt;
+use hal::{
+    pac::{uarte0, Interrupt, UARTE0},
+    uarte::{Baudrate, Parity, Uarte},
+};
+
+/// The interrupt to forward to `SerialRx::handle_interrupt`.
+pub const INTERRUPT: Interrupt = Interrupt::UARTE0_UART0;
+
+/// The transmitting half of the UART.
+pub type Tx = Uarte<UARTE0>;
+
+/// Enable the UART, at 115200 baud.
+pub fn open(uarte: UARTE0, pins: UartPins) -> Tx {
+    Uarte::new(uarte, pins.into(), Parity::EXCLUDED, Baudrate::BAUD115200)
+}
+
+/// Transmit `bytes`, waiting until they have all gone.
+pub fn write_bytes(tx: &mut Tx, bytes: &[u8]) -> fmt::Result {
+    tx.write(bytes).map_err(|_| fmt::Error)
+}
+
+pub struct SerialRx {
+    buffers: [[u8; 1]; 2],
+    /// The buffer that the current reception is going into.
+    current: usize,
+    bytes: RxProducer,
+}
+
+impl SerialRx {
+    pub fn new(bytes: RxProducer) -> Self {
+        Self {
+            buffers: [[0; 1]; 2],
+            current: 0,
+            bytes,
+        }
+    }
+
+    /// Begin receiving. The UART must already be enabled, and its interrupt
+    /// unmasked and forwarded to `handle_interrupt`. The buffers are handed
+    /// to the hardware, so this must not be moved once started.
+    pub fn start(&mut self) {
+        let uarte = uarte();
+        uarte.shorts.write(|w| w.endrx_startrx().enabled());
+        uarte
+            .intenset
+            .write(|w| w.endrx().set().rxstarted().set().error().set());
+        self.point_at(0);
+        uarte.tasks_startrx.write(|w| unsafe { w.bits(1) });
+    }
+
+    pub fn handle_interrupt(&mut self) {
+        let uarte = uarte();
+
+        // A finished reception comes before the one started after it
+        if uarte.events_endrx.read().bits() != 0 {
+            uarte.events_endrx.reset();
+            let amount = uarte.rxd.amount.read().bits() as usize;
+            let buffer = &self.buffers[self.current];
+            // A full queue counts the drop for the main loop to report
+            let _ = self
+                .bytes
+                .enqueue_slice(&buffer[..amount.min(buffer.len())]);
+            self.current ^= 1;
+        }
+
+        if uarte.events_rxstarted.read().bits() != 0 {
+            uarte.events_rxstarted.reset();
+            self.point_at(self.current ^ 1);
+        }
+
+        if uarte.events_error.read().bits() != 0 {
+            uarte.events_error.reset();
+            let source = uarte.errorsrc.read();
+            if source.overrun().bit_is_set() {
+                count_overrun();
+            }
+            // Writing the bits back clears them
+            uarte.errorsrc.write(|w| unsafe { w.bits(source.bits()) });
+        }
+    }
+
+    fn point_at(&mut self, index: usize) {
+        let buffer = &mut self.buffers[index];
+        let uarte = uarte();
+        uarte
+            .rxd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(buffer.as_mut_ptr() as u32) });
+        uarte
+            .rxd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(buffer.len() as _) });
+    }
+}
+
+fn uarte() -> &'static uarte0::RegisterBlock {
+    // Safe as the HAL's handle on the UART only ever transmits, leaving
+    // the receive registers to us
+    unsafe { &*UARTE0::ptr() }
+}
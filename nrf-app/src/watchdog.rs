@@ -0,0 +1,87 @@
+//! The hardware watchdog, fed only while every supervised task keeps up.
+//!
+//! Once the watchdog times out there are two low frequency clock ticks
+//! before the reset, which is just enough to keep the task that was
+//! furthest behind for the next boot to report.
+
+use app::watchdog::{Missed, MissedRecord, Supervisor, Task};
+use core::mem::MaybeUninit;
+use core::ptr;
+use hal::{
+    pac::WDT,
+    wdt::{count::One, handles::Hdl0, Watchdog, WatchdogHandle},
+};
+
+/// How long the watchdog waits to be fed, comfortably longer than any
+/// task's deadline.
+const TIMEOUT_MS: u32 = 2_000;
+const TICKS_PER_SECOND: u32 = 32_768;
+
+/// The tasks supervised, by their index in `TASKS`.
+#[derive(Clone, Copy, Debug)]
+pub enum TaskId {
+    MainLoop,
+    Display,
+}
+
+pub const TASKS: [Task; 2] = [
+    Task {
+        name: "main loop",
+        deadline_ms: 1_000,
+    },
+    Task {
+        name: "display",
+        deadline_ms: 100,
+    },
+];
+
+pub type TaskSupervisor = Supervisor<{ TASKS.len() }>;
+
+#[link_section = ".noinit.MISSED"]
+static mut MISSED: MaybeUninit<MissedRecord> = MaybeUninit::uninit();
+
+fn record() -> &'static mut MissedRecord {
+    // Safe as every bit pattern is a record, if not a valid one, and only
+    // startup and the watchdog's interrupt touch it, neither of which is
+    // ever interrupted by the other
+    unsafe { &mut *(ptr::addr_of_mut!(MISSED) as *mut MissedRecord) }
+}
+
+/// Start the watchdog, or carry on with it if it survived a soft reset,
+/// returning the handle that feeds it. Its interrupt must be unmasked and
+/// forwarded to `handle_timeout`.
+pub fn start(wdt: WDT) -> WatchdogHandle<Hdl0> {
+    let parts = match Watchdog::try_new(wdt) {
+        Ok(mut watchdog) => {
+            watchdog.set_lfosc_ticks(TIMEOUT_MS * TICKS_PER_SECOND / 1_000);
+            watchdog.enable_interrupt();
+            watchdog.run_during_sleep(true);
+            // Stopping at a breakpoint shouldn't count as stalling
+            watchdog.run_during_debug_halt(false);
+            watchdog.activate::<One>()
+        }
+        // Still running, and configured, from before a soft reset
+        Err(wdt) => match Watchdog::try_recover::<One>(wdt) {
+            Ok(parts) => parts,
+            Err(_) => panic!("Watchdog running with other handles"),
+        },
+    };
+    parts.handles.0
+}
+
+/// Keep the task furthest behind for after the reset that is coming.
+pub fn handle_timeout(missed: Option<Missed>) {
+    if let Some(missed) = missed {
+        record().record(&missed);
+    }
+}
+
+/// Hand any missed deadline kept from before the last reset to `f`, then
+/// forget it.
+pub fn take_last(f: impl FnOnce(&Missed)) {
+    let record = record();
+    if let Some(missed) = record.missed() {
+        f(&missed);
+    }
+    record.clear();
+}